@@ -0,0 +1,127 @@
+//! Bytes read from disk to find the EXIF block of a large JPEG: the APP1
+//! segment walker against handing the whole file to the EXIF crate.
+//!
+//! Run with `cargo bench --bench jpeg_read`.
+
+use ptime::container::read_jpeg_exif;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Size of the entropy-coded image data, typical of a 24 MP camera JPEG
+const SCAN_SIZE: usize = 30 * 1024 * 1024;
+const ROUNDS: u32 = 20;
+
+/// Counts the bytes actually pulled from the underlying file.
+struct Counting<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Counting<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn segment(marker: u8, data: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(data);
+    segment
+}
+
+/// Minimal little-endian TIFF structure with a single `DateTime` tag
+fn exif_segment() -> Vec<u8> {
+    let value = b"2019:07:15 10:11:12\0";
+    let mut data = b"Exif\0\0II*\0\x08\0\0\0".to_vec();
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&0x0132u16.to_le_bytes());
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(&26u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(value);
+    segment(0xE1, &data)
+}
+
+fn write_jpeg(path: &Path, segments: &[Vec<u8>]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(File::create(path)?);
+    file.write_all(&[0xFF, 0xD8])?;
+    for segment in segments {
+        file.write_all(segment)?;
+    }
+    file.write_all(&[0xFF, 0xDA, 0, 2])?;
+    // Scan data never contains a bare 0xFF marker prefix
+    let chunk = vec![0x5A; 1024 * 1024];
+    for _ in 0..SCAN_SIZE / chunk.len() {
+        file.write_all(&chunk)?;
+    }
+    file.write_all(&[0xFF, 0xD9])?;
+    file.flush()
+}
+
+/// Average bytes read and time per file over `ROUNDS` reads.
+fn measure(path: &Path, read: impl Fn(&mut BufReader<Counting<File>>)) -> (u64, Duration) {
+    let mut bytes = 0;
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let file = File::open(path).expect("benchmark file exists");
+        let mut reader = BufReader::new(Counting {
+            inner: file,
+            bytes: 0,
+        });
+        read(&mut reader);
+        bytes += reader.into_inner().bytes;
+    }
+    (bytes / u64::from(ROUNDS), start.elapsed() / ROUNDS)
+}
+
+fn main() -> io::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let icc = segment(0xE2, &vec![0x11; 60_000]);
+    let layouts = [
+        ("EXIF first", vec![exif_segment()]),
+        (
+            "EXIF after ICC profile",
+            vec![
+                segment(0xE0, b"JFIF\0\x01\x01"),
+                icc.clone(),
+                icc,
+                exif_segment(),
+            ],
+        ),
+        ("no EXIF", vec![segment(0xE0, b"JFIF\0\x01\x01")]),
+    ];
+
+    println!(
+        "{:<24} {:>14} {:>10} {:>14} {:>10}",
+        "layout", "walker bytes", "time", "full bytes", "time"
+    );
+    for (name, segments) in layouts {
+        let path = dir.path().join("bench.jpg");
+        write_jpeg(&path, &segments)?;
+
+        let (walker_bytes, walker_time) = measure(&path, |reader| {
+            if let Ok(Some(payload)) = read_jpeg_exif(reader) {
+                let _ = exif::Reader::new().read_raw(payload);
+            }
+        });
+        let (full_bytes, full_time) = measure(&path, |reader| {
+            let _ = exif::Reader::new().read_from_container(reader);
+        });
+        println!(
+            "{name:<24} {walker_bytes:>14} {walker_time:>10.2?} {full_bytes:>14} {full_time:>10.2?}"
+        );
+    }
+    Ok(())
+}
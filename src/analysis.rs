@@ -1,65 +1,646 @@
-use crate::metadata::PhotoMeta;
-use chrono::Datelike;
+use crate::metadata::{DateSource, PhotoMeta, Precision, SkipReason};
+use chrono::{Datelike, Duration, NaiveDate, Timelike, Weekday};
+use clap::ValueEnum;
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::fmt;
 
-pub fn find_oldest(photos: &[PhotoMeta]) -> Option<&PhotoMeta> {
-    if photos.is_empty() {
-        return None;
+/// Consumes photos one at a time as the scan produces them, so a summary of
+/// any number of files takes constant memory (plus one entry per bucket for
+/// histograms).
+pub trait Accumulator {
+    type Output;
+
+    fn add(&mut self, photo: PhotoMeta);
+
+    /// Notes a scanned file that yields no photo. Most summaries ignore it.
+    fn skip(&mut self, _reason: SkipReason) {}
+
+    fn finish(self) -> Self::Output;
+
+    /// Feeds all `photos` and returns the result.
+    fn fold<I>(mut self, photos: I) -> Self::Output
+    where
+        Self: Sized,
+        I: IntoIterator<Item = PhotoMeta>,
+    {
+        for photo in photos {
+            self.add(photo);
+        }
+        self.finish()
     }
+}
 
-    photos.iter().min_by(|a, b| {
-        a.date
-            .cmp(&b.date)
-            .then_with(|| a.rel_path.cmp(&b.rel_path))
-    })
+/// Keeps the photo taken first; see [`oldest_order`].
+#[derive(Debug, Default)]
+pub struct Oldest(Option<PhotoMeta>);
+
+impl Accumulator for Oldest {
+    type Output = Option<PhotoMeta>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        match &self.0 {
+            Some(oldest) if oldest_order(&photo, oldest) != Ordering::Less => {}
+            _ => self.0 = Some(photo),
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        self.0
+    }
 }
 
-pub fn find_latest(photos: &[PhotoMeta]) -> Option<&PhotoMeta> {
-    if photos.is_empty() {
-        return None;
+/// Keeps the photo taken last; see [`latest_order`].
+#[derive(Debug, Default)]
+pub struct Latest(Option<PhotoMeta>);
+
+impl Accumulator for Latest {
+    type Output = Option<PhotoMeta>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        match &self.0 {
+            Some(latest) if latest_order(&photo, latest) != Ordering::Greater => {}
+            _ => self.0 = Some(photo),
+        }
     }
 
-    photos.iter().max_by(|a, b| {
-        a.date
-            .cmp(&b.date)
-            .then_with(|| b.rel_path.cmp(&a.rel_path)) // reverse for lexicographic
-    })
+    fn finish(self) -> Self::Output {
+        self.0
+    }
+}
+
+/// Unit of time a histogram counts photos by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Granularity {
+    #[default]
+    Year,
+    /// Calendar quarter, e.g. 2023-Q1
+    Quarter,
+    Month,
+    /// ISO 8601 week, e.g. 2023-W05
+    Week,
+    Day,
+    /// Hour of the day, across all dates
+    Hour,
+    /// Day of the week, across all dates
+    Weekday,
+}
+
+impl Granularity {
+    /// The bucket `photo` falls in, or `None` when its date is known less
+    /// precisely than the unit, e.g. a year-only scan in a month histogram.
+    pub fn bucket(self, photo: &PhotoMeta) -> Option<Bucket> {
+        if photo.precision < self.precision() {
+            return None;
+        }
+        let datetime = photo.datetime;
+        Some(match self {
+            Granularity::Year => Bucket::Year(datetime.year()),
+            Granularity::Quarter => Bucket::Quarter {
+                year: datetime.year(),
+                quarter: datetime.month0() / 3 + 1,
+            },
+            Granularity::Month => Bucket::Month {
+                year: datetime.year(),
+                month: datetime.month(),
+            },
+            Granularity::Week => {
+                let week = datetime.iso_week();
+                Bucket::Week {
+                    year: week.year(),
+                    week: week.week(),
+                }
+            }
+            Granularity::Day => Bucket::Day(datetime.date()),
+            Granularity::Hour => Bucket::Hour(datetime.hour()),
+            Granularity::Weekday => Bucket::Weekday(datetime.weekday().num_days_from_monday()),
+        })
+    }
+
+    /// Precision a date needs to be placed in a bucket.
+    pub fn precision(self) -> Precision {
+        match self {
+            Granularity::Year => Precision::Year,
+            Granularity::Quarter | Granularity::Month => Precision::Month,
+            Granularity::Week | Granularity::Day | Granularity::Weekday => Precision::Day,
+            Granularity::Hour => Precision::Time,
+        }
+    }
+}
+
+/// Histogram key. Keys of one histogram all share a variant, so they sort
+/// chronologically (or Monday first for weekdays).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Bucket {
+    Year(i32),
+    Quarter {
+        year: i32,
+        quarter: u32,
+    },
+    Month {
+        year: i32,
+        month: u32,
+    },
+    /// ISO week-numbering year and week
+    Week {
+        year: i32,
+        week: u32,
+    },
+    Day(NaiveDate),
+    /// 0 to 23
+    Hour(u32),
+    /// Days from Monday, 0 to 6
+    Weekday(u32),
+}
+
+impl Bucket {
+    /// The following bucket of the same unit, if any.
+    fn next(self) -> Option<Bucket> {
+        Some(match self {
+            Bucket::Year(year) => Bucket::Year(year.checked_add(1)?),
+            Bucket::Quarter { year, quarter: 4 } => Bucket::Quarter {
+                year: year.checked_add(1)?,
+                quarter: 1,
+            },
+            Bucket::Quarter { year, quarter } => Bucket::Quarter {
+                year,
+                quarter: quarter + 1,
+            },
+            Bucket::Month { year, month: 12 } => Bucket::Month {
+                year: year.checked_add(1)?,
+                month: 1,
+            },
+            Bucket::Month { year, month } => Bucket::Month {
+                year,
+                month: month + 1,
+            },
+            Bucket::Week { year, week } => {
+                let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)?;
+                let next = (monday + Duration::weeks(1)).iso_week();
+                Bucket::Week {
+                    year: next.year(),
+                    week: next.week(),
+                }
+            }
+            Bucket::Day(date) => Bucket::Day(date.succ_opt()?),
+            Bucket::Hour(hour) if hour < 23 => Bucket::Hour(hour + 1),
+            Bucket::Weekday(day) if day < 6 => Bucket::Weekday(day + 1),
+            Bucket::Hour(_) | Bucket::Weekday(_) => return None,
+        })
+    }
+
+    /// Buckets to show from `first` to `last`: every one in between, and the
+    /// whole day or week for the cyclic units.
+    fn span(first: Bucket, last: Bucket) -> (Bucket, Bucket) {
+        match first {
+            Bucket::Hour(_) => (Bucket::Hour(0), Bucket::Hour(23)),
+            Bucket::Weekday(_) => (Bucket::Weekday(0), Bucket::Weekday(6)),
+            _ => (first, last),
+        }
+    }
+}
+
+/// Labels as printed in histograms: `2023`, `2023-Q1`, `2023-01`,
+/// `2023-W05`, `2023-01-15`, `14:00`, `Mon`
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bucket::Year(year) => write!(f, "{year}"),
+            Bucket::Quarter { year, quarter } => write!(f, "{year}-Q{quarter}"),
+            Bucket::Month { year, month } => write!(f, "{year}-{month:02}"),
+            Bucket::Week { year, week } => write!(f, "{year}-W{week:02}"),
+            Bucket::Day(date) => write!(f, "{date}"),
+            Bucket::Hour(hour) => write!(f, "{hour:02}:00"),
+            Bucket::Weekday(day) => {
+                let weekday = Weekday::try_from(*day as u8).map_err(|_| fmt::Error)?;
+                write!(f, "{weekday}")
+            }
+        }
+    }
+}
+
+/// Values per bucket, with every bucket between the first and the last one
+/// present.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Binned<V> {
+    pub buckets: BTreeMap<Bucket, V>,
+    /// Photos whose date is too coarse for the histogram's unit
+    pub unplaced: usize,
+}
+
+impl<V: Default> Binned<V> {
+    fn fill_gaps(&mut self) {
+        let (Some(&first), Some(&last)) = (self.buckets.keys().next(), self.buckets.keys().last())
+        else {
+            return;
+        };
+        let (mut bucket, last) = Bucket::span(first, last);
+        loop {
+            self.buckets.entry(bucket).or_default();
+            match bucket.next() {
+                Some(next) if bucket != last => bucket = next,
+                _ => break,
+            }
+        }
+    }
+}
+
+impl<V> Binned<V> {
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty() && self.unplaced == 0
+    }
+}
+
+/// Counts photos per bucket of `granularity`. Buckets between the first
+/// and last one are present with a zero count.
+#[derive(Debug, Default)]
+pub struct Histogram {
+    granularity: Granularity,
+    counts: Binned<usize>,
+}
+
+impl Histogram {
+    pub fn new(granularity: Granularity) -> Self {
+        Histogram {
+            granularity,
+            counts: Binned::default(),
+        }
+    }
+}
+
+impl Accumulator for Histogram {
+    type Output = Binned<usize>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        match self.granularity.bucket(&photo) {
+            Some(bucket) => *self.counts.buckets.entry(bucket).or_insert(0) += 1,
+            None => self.counts.unplaced += 1,
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        let mut counts = self.counts;
+        counts.fill_gaps();
+        counts
+    }
+}
+
+/// Per-bucket counts split by the source each date was read from. Buckets
+/// between the first and last one are present with no sources.
+#[derive(Debug, Default)]
+pub struct SourceHistogram {
+    granularity: Granularity,
+    counts: Binned<BTreeMap<DateSource, usize>>,
+}
+
+impl SourceHistogram {
+    pub fn new(granularity: Granularity) -> Self {
+        SourceHistogram {
+            granularity,
+            counts: Binned::default(),
+        }
+    }
+}
+
+impl Accumulator for SourceHistogram {
+    type Output = Binned<BTreeMap<DateSource, usize>>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        match self.granularity.bucket(&photo) {
+            Some(bucket) => {
+                *self
+                    .counts
+                    .buckets
+                    .entry(bucket)
+                    .or_default()
+                    .entry(photo.source)
+                    .or_insert(0) += 1
+            }
+            None => self.counts.unplaced += 1,
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        let mut counts = self.counts;
+        counts.fill_gaps();
+        counts
+    }
+}
+
+/// Photos per day of one year, as drawn by `calendar`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearDays {
+    pub year: i32,
+    /// Days with at least one photo
+    pub days: BTreeMap<NaiveDate, usize>,
+    /// Photos of that year not dated to the day
+    pub unplaced: usize,
+}
+
+impl YearDays {
+    pub fn photos(&self) -> usize {
+        self.days.values().sum()
+    }
+}
+
+/// Counts photos per day of `year`, or of the latest year with a photo
+/// dated to the day. Only that year's days are kept, so memory stays
+/// bounded whatever the span of the library.
+#[derive(Debug, Default)]
+pub struct DayCounts {
+    year: Option<i32>,
+    days: BTreeMap<NaiveDate, usize>,
+    /// Per year, since the year shown is only known at the end
+    unplaced: BTreeMap<i32, usize>,
+}
+
+impl DayCounts {
+    pub fn new(year: Option<i32>) -> Self {
+        DayCounts {
+            year,
+            ..DayCounts::default()
+        }
+    }
+
+    fn latest_day_year(&self) -> Option<i32> {
+        self.days.keys().next_back().map(|day| day.year())
+    }
+}
+
+impl Accumulator for DayCounts {
+    type Output = Option<YearDays>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        let year = photo.datetime.year();
+        if self.year.is_some_and(|wanted| wanted != year) {
+            return;
+        }
+        if photo.precision < Precision::Day {
+            *self.unplaced.entry(year).or_insert(0) += 1;
+            return;
+        }
+        match self.latest_day_year() {
+            Some(latest) if latest > year => return,
+            Some(latest) if latest < year => self.days.clear(),
+            _ => {}
+        }
+        *self.days.entry(photo.date()).or_insert(0) += 1;
+    }
+
+    fn finish(mut self) -> Self::Output {
+        let year = self
+            .year
+            .or_else(|| self.latest_day_year())
+            .or_else(|| self.unplaced.keys().next_back().copied())?;
+        let unplaced = self.unplaced.remove(&year).unwrap_or(0);
+        if self.days.is_empty() && unplaced == 0 {
+            return None;
+        }
+        Some(YearDays {
+            year,
+            days: self.days,
+            unplaced,
+        })
+    }
+}
+
+/// Photos per hour of the day for each weekday, as drawn by `punchcard`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeekHours {
+    /// Indexed by days from Monday, then hour
+    pub counts: [[usize; 24]; 7],
+    /// Photos without a time of day
+    pub unplaced: usize,
+}
+
+impl WeekHours {
+    pub fn max(&self) -> usize {
+        self.counts.iter().flatten().copied().max().unwrap_or(0)
+    }
+}
+
+/// Counts photos by weekday and hour of the capture time.
+#[derive(Debug, Default)]
+pub struct Punchcard {
+    hours: WeekHours,
+    photos: usize,
+}
+
+impl Accumulator for Punchcard {
+    type Output = Option<WeekHours>;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        self.photos += 1;
+        if photo.precision < Precision::Time {
+            self.hours.unplaced += 1;
+            return;
+        }
+        let weekday = photo.datetime.weekday().num_days_from_monday() as usize;
+        self.hours.counts[weekday][photo.datetime.hour() as usize] += 1;
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.photos > 0).then_some(self.hours)
+    }
+}
+
+/// Percentiles reported by `stats`, the 50th being the median.
+pub const PERCENTILES: [usize; 5] = [10, 25, 50, 75, 90];
+
+/// Everything `stats` reports about a library.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    /// Files read, whether they yielded a photo or not
+    pub scanned: usize,
+    pub dated: usize,
+    pub skipped: BTreeMap<SkipReason, usize>,
+    pub oldest: Option<PhotoMeta>,
+    pub latest: Option<PhotoMeta>,
+    /// Day by which each of [`PERCENTILES`] percent of photos were taken,
+    /// partial dates counting at the start of their period
+    pub percentiles: Vec<(usize, NaiveDate)>,
+    pub busiest_year: Option<(Bucket, usize)>,
+    /// Among photos dated to the month
+    pub busiest_month: Option<(Bucket, usize)>,
+    /// Among photos dated to the day
+    pub busiest_day: Option<(Bucket, usize)>,
+    /// Days with at least one photo dated to the day
+    pub active_days: usize,
+    /// Photos dated to the day
+    pub day_photos: usize,
 }
 
-pub fn build_histogram(photos: &[PhotoMeta]) -> BTreeMap<i32, usize> {
-    if photos.is_empty() {
-        return BTreeMap::new();
+impl Summary {
+    pub fn median(&self) -> Option<NaiveDate> {
+        self.percentiles
+            .iter()
+            .find(|(percent, _)| *percent == 50)
+            .map(|(_, date)| *date)
     }
 
-    // Count photos per year
-    let mut year_counts = BTreeMap::new();
-    for photo in photos {
-        *year_counts.entry(photo.date.year()).or_insert(0) += 1;
+    /// Mean number of photos on a day with at least one.
+    pub fn photos_per_active_day(&self) -> Option<f64> {
+        (self.active_days > 0).then(|| self.day_photos as f64 / self.active_days as f64)
+    }
+}
+
+/// Gathers a [`Summary`] in one pass. Counts are kept per day, so memory
+/// grows with the number of distinct days rather than photos.
+#[derive(Debug, Default)]
+pub struct Stats {
+    oldest: Oldest,
+    latest: Latest,
+    skipped: BTreeMap<SkipReason, usize>,
+    /// Photos per start day of their date, for percentiles
+    starts: BTreeMap<NaiveDate, usize>,
+    years: BTreeMap<Bucket, usize>,
+    months: BTreeMap<Bucket, usize>,
+    days: BTreeMap<Bucket, usize>,
+}
+
+impl Accumulator for Stats {
+    type Output = Summary;
+
+    fn add(&mut self, photo: PhotoMeta) {
+        *self.starts.entry(photo.date()).or_insert(0) += 1;
+        for (granularity, counts) in [
+            (Granularity::Year, &mut self.years),
+            (Granularity::Month, &mut self.months),
+            (Granularity::Day, &mut self.days),
+        ] {
+            if let Some(bucket) = granularity.bucket(&photo) {
+                *counts.entry(bucket).or_insert(0) += 1;
+            }
+        }
+        self.oldest.add(photo.clone());
+        self.latest.add(photo);
     }
 
-    // Find min and max years
-    let min_year = *year_counts.keys().min().unwrap();
-    let max_year = *year_counts.keys().max().unwrap();
+    fn skip(&mut self, reason: SkipReason) {
+        *self.skipped.entry(reason).or_insert(0) += 1;
+    }
 
-    // Fill in all years between min and max with zero counts
-    let mut complete_histogram = BTreeMap::new();
-    for year in min_year..=max_year {
-        complete_histogram.insert(year, *year_counts.get(&year).unwrap_or(&0));
+    fn finish(self) -> Self::Output {
+        let dated: usize = self.starts.values().sum();
+        let percentiles = PERCENTILES
+            .iter()
+            .filter_map(|&percent| Some((percent, percentile(&self.starts, dated, percent)?)))
+            .collect();
+        Summary {
+            scanned: dated + self.skipped.values().sum::<usize>(),
+            dated,
+            skipped: self.skipped,
+            oldest: self.oldest.finish(),
+            latest: self.latest.finish(),
+            percentiles,
+            busiest_year: busiest(&self.years),
+            busiest_month: busiest(&self.months),
+            busiest_day: busiest(&self.days),
+            active_days: self.days.len(),
+            day_photos: self.days.values().sum(),
+        }
     }
+}
 
-    complete_histogram
+/// Nearest-rank percentile: the first day by which at least `percent`
+/// percent of the `total` photos were taken.
+fn percentile(
+    days: &BTreeMap<NaiveDate, usize>,
+    total: usize,
+    percent: usize,
+) -> Option<NaiveDate> {
+    let rank = (total * percent).div_ceil(100).max(1);
+    let mut seen = 0;
+    days.iter().find_map(|(&day, &count)| {
+        seen += count;
+        (seen >= rank).then_some(day)
+    })
+}
+
+/// Bucket with the most photos, the earliest one on ties.
+fn busiest(counts: &BTreeMap<Bucket, usize>) -> Option<(Bucket, usize)> {
+    counts
+        .iter()
+        .fold(None, |best, (&bucket, &count)| match best {
+            Some((_, most)) if most >= count => best,
+            _ => Some((bucket, count)),
+        })
+}
+
+/// Orders photos so that the oldest one compares least. Partial dates sort
+/// at the start of their period, so `1987` counts as 1987-01-01 00:00.
+/// Between photos dated to the same instant, the more precise date wins,
+/// then an exact date over an approximate one, then the first path.
+pub fn oldest_order(a: &PhotoMeta, b: &PhotoMeta) -> Ordering {
+    a.datetime
+        .cmp(&b.datetime)
+        .then_with(|| b.precision.cmp(&a.precision))
+        .then_with(|| a.approximate.cmp(&b.approximate))
+        .then_with(|| a.rel_path.cmp(&b.rel_path))
+}
+
+/// Orders photos so that the latest one compares greatest, with the same
+/// tie-breaks as [`oldest_order`]: the more precise date, then an exact
+/// one, then the first path.
+pub fn latest_order(a: &PhotoMeta, b: &PhotoMeta) -> Ordering {
+    a.datetime
+        .cmp(&b.datetime)
+        .then_with(|| a.precision.cmp(&b.precision))
+        .then_with(|| b.approximate.cmp(&a.approximate))
+        .then_with(|| b.rel_path.cmp(&a.rel_path)) // reverse for lexicographic
+}
+
+pub fn find_oldest(photos: &[PhotoMeta]) -> Option<&PhotoMeta> {
+    photos.iter().min_by(|a, b| oldest_order(a, b))
+}
+
+pub fn find_latest(photos: &[PhotoMeta]) -> Option<&PhotoMeta> {
+    photos.iter().max_by(|a, b| latest_order(a, b))
+}
+
+pub fn build_histogram(photos: &[PhotoMeta], granularity: Granularity) -> Binned<usize> {
+    Histogram::new(granularity).fold(photos.iter().cloned())
+}
+
+pub fn build_source_histogram(
+    photos: &[PhotoMeta],
+    granularity: Granularity,
+) -> Binned<BTreeMap<DateSource, usize>> {
+    SourceHistogram::new(granularity).fold(photos.iter().cloned())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
+    use crate::datetime::Normalizations;
+    use crate::metadata::Precision;
+    use chrono::{NaiveDate, Timelike};
     use std::path::PathBuf;
 
     fn make_photo(path: &str, year: i32, month: u32, day: u32) -> PhotoMeta {
+        make_photo_at(path, year, month, day, 0, 0, 0)
+    }
+
+    fn make_photo_at(
+        path: &str,
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        min: u32,
+        sec: u32,
+    ) -> PhotoMeta {
         PhotoMeta {
             rel_path: PathBuf::from(path),
-            date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+            datetime: NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(hour, min, sec)
+                .unwrap(),
+            offset: None,
+            source: DateSource::Original,
+            precision: Precision::Time,
+            approximate: false,
+            normalized: Normalizations::default(),
         }
     }
 
@@ -85,7 +666,7 @@ mod tests {
         ];
         let result = find_oldest(&photos).unwrap();
         assert_eq!(result.rel_path, PathBuf::from("a.jpg"));
-        assert_eq!(result.date.year(), 2020);
+        assert_eq!(result.datetime.year(), 2020);
     }
 
     #[test]
@@ -99,6 +680,27 @@ mod tests {
         assert_eq!(result.rel_path, PathBuf::from("a.jpg"));
     }
 
+    #[test]
+    fn test_find_oldest_same_day_uses_time() {
+        let photos = vec![
+            make_photo_at("IMG_0001.jpg", 2020, 1, 1, 9, 0, 1),
+            make_photo_at("IMG_9999.jpg", 2020, 1, 1, 9, 0, 0),
+        ];
+        let result = find_oldest(&photos).unwrap();
+        assert_eq!(result.rel_path, PathBuf::from("IMG_9999.jpg"));
+    }
+
+    #[test]
+    fn test_find_oldest_subsecond_precision() {
+        let mut early = make_photo_at("b.jpg", 2020, 1, 1, 9, 0, 0);
+        early.datetime = early.datetime.with_nanosecond(100_000_000).unwrap();
+        let mut late = make_photo_at("a.jpg", 2020, 1, 1, 9, 0, 0);
+        late.datetime = late.datetime.with_nanosecond(200_000_000).unwrap();
+        let photos = vec![late, early];
+        let result = find_oldest(&photos).unwrap();
+        assert_eq!(result.rel_path, PathBuf::from("b.jpg"));
+    }
+
     #[test]
     fn test_find_latest_empty() {
         let photos: Vec<PhotoMeta> = vec![];
@@ -121,7 +723,7 @@ mod tests {
         ];
         let result = find_latest(&photos).unwrap();
         assert_eq!(result.rel_path, PathBuf::from("c.jpg"));
-        assert_eq!(result.date.year(), 2022);
+        assert_eq!(result.datetime.year(), 2022);
     }
 
     #[test]
@@ -135,10 +737,127 @@ mod tests {
         assert_eq!(result.rel_path, PathBuf::from("a.jpg"));
     }
 
+    #[test]
+    fn test_find_latest_same_day_uses_time() {
+        let photos = vec![
+            make_photo_at("IMG_0001.jpg", 2023, 12, 31, 23, 59, 59),
+            make_photo_at("IMG_9999.jpg", 2023, 12, 31, 8, 0, 0),
+        ];
+        let result = find_latest(&photos).unwrap();
+        assert_eq!(result.rel_path, PathBuf::from("IMG_0001.jpg"));
+    }
+
+    fn make_partial(path: &str, year: i32, precision: Precision, approximate: bool) -> PhotoMeta {
+        PhotoMeta {
+            precision,
+            approximate,
+            ..make_photo(path, year, 1, 1)
+        }
+    }
+
+    #[test]
+    fn test_partial_dates_sort_at_start_of_period() {
+        let photos = vec![
+            make_photo("b.jpg", 1987, 3, 1),
+            make_partial("scans/a.jpg", 1987, Precision::Year, true),
+        ];
+        assert_eq!(
+            find_oldest(&photos).unwrap().rel_path,
+            PathBuf::from("scans/a.jpg")
+        );
+        assert_eq!(
+            find_latest(&photos).unwrap().rel_path,
+            PathBuf::from("b.jpg")
+        );
+    }
+
+    #[test]
+    fn test_precise_dates_win_ties_with_partial_ones() {
+        let photos = vec![
+            make_partial("a.jpg", 1987, Precision::Year, true),
+            make_partial("b.jpg", 1987, Precision::Year, false),
+            make_partial("c.jpg", 1987, Precision::Month, false),
+            make_photo("d.jpg", 1987, 1, 1),
+        ];
+        assert_eq!(
+            find_oldest(&photos).unwrap().rel_path,
+            PathBuf::from("d.jpg")
+        );
+        assert_eq!(
+            find_latest(&photos).unwrap().rel_path,
+            PathBuf::from("d.jpg")
+        );
+
+        let photos = &photos[..2];
+        assert_eq!(
+            find_oldest(photos).unwrap().rel_path,
+            PathBuf::from("b.jpg")
+        );
+        assert_eq!(
+            find_latest(photos).unwrap().rel_path,
+            PathBuf::from("b.jpg")
+        );
+    }
+
+    #[test]
+    fn test_build_histogram_counts_year_only_dates() {
+        let photos = vec![
+            make_partial("a.jpg", 1987, Precision::Year, true),
+            make_photo("b.jpg", 1989, 7, 1),
+        ];
+        let hist = build_histogram(&photos, Granularity::Year);
+        assert_eq!(hist.buckets.get(&Bucket::Year(1987)), Some(&1));
+        assert_eq!(hist.buckets.get(&Bucket::Year(1988)), Some(&0));
+    }
+
+    #[test]
+    fn test_accumulators_match_slice_functions() {
+        let photos = vec![
+            make_photo("b.jpg", 2021, 5, 1),
+            make_photo("a.jpg", 2019, 1, 1),
+            make_photo("c.jpg", 2019, 1, 1),
+            make_partial("d.jpg", 2019, Precision::Year, true),
+            make_photo("e.jpg", 2021, 5, 1),
+        ];
+        assert_eq!(
+            Oldest::default().fold(photos.clone()).as_ref(),
+            find_oldest(&photos)
+        );
+        assert_eq!(
+            Latest::default().fold(photos.clone()).as_ref(),
+            find_latest(&photos)
+        );
+        // The result does not depend on the order photos arrive in
+        for rotation in 0..photos.len() {
+            let mut rotated = photos.clone();
+            rotated.rotate_left(rotation);
+            let oldest = Oldest::default().fold(rotated.clone()).unwrap();
+            assert_eq!(oldest.rel_path, PathBuf::from("a.jpg"));
+            let latest = Latest::default().fold(rotated).unwrap();
+            assert_eq!(latest.rel_path, PathBuf::from("b.jpg"));
+        }
+        assert_eq!(
+            Histogram::default().fold(photos.clone()).buckets,
+            BTreeMap::from([
+                (Bucket::Year(2019), 3),
+                (Bucket::Year(2020), 0),
+                (Bucket::Year(2021), 2)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_accumulators_empty() {
+        assert_eq!(Oldest::default().finish(), None);
+        assert_eq!(Latest::default().finish(), None);
+        assert!(Histogram::default().finish().is_empty());
+        assert!(SourceHistogram::default().finish().is_empty());
+    }
+
     #[test]
     fn test_build_histogram_empty() {
         let photos: Vec<PhotoMeta> = vec![];
-        let hist = build_histogram(&photos);
+        let hist = build_histogram(&photos, Granularity::Year);
         assert!(hist.is_empty());
     }
 
@@ -149,9 +868,9 @@ mod tests {
             make_photo("b.jpg", 2020, 6, 15),
             make_photo("c.jpg", 2020, 12, 31),
         ];
-        let hist = build_histogram(&photos);
-        assert_eq!(hist.len(), 1);
-        assert_eq!(hist.get(&2020), Some(&3));
+        let hist = build_histogram(&photos, Granularity::Year);
+        assert_eq!(hist.buckets.len(), 1);
+        assert_eq!(hist.buckets.get(&Bucket::Year(2020)), Some(&3));
     }
 
     #[test]
@@ -162,12 +881,12 @@ mod tests {
             make_photo("c.jpg", 2022, 3, 10),
             make_photo("d.jpg", 2023, 7, 20),
         ];
-        let hist = build_histogram(&photos);
-        assert_eq!(hist.len(), 4); // 2020, 2021 (gap), 2022, 2023
-        assert_eq!(hist.get(&2020), Some(&2));
-        assert_eq!(hist.get(&2021), Some(&0)); // Gap filled
-        assert_eq!(hist.get(&2022), Some(&1));
-        assert_eq!(hist.get(&2023), Some(&1));
+        let hist = build_histogram(&photos, Granularity::Year);
+        assert_eq!(hist.buckets.len(), 4); // 2020, 2021 (gap), 2022, 2023
+        assert_eq!(hist.buckets.get(&Bucket::Year(2020)), Some(&2));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2021)), Some(&0)); // Gap filled
+        assert_eq!(hist.buckets.get(&Bucket::Year(2022)), Some(&1));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2023)), Some(&1));
     }
 
     #[test]
@@ -176,12 +895,291 @@ mod tests {
             make_photo("a.jpg", 2018, 1, 1),
             make_photo("b.jpg", 2022, 1, 1),
         ];
-        let hist = build_histogram(&photos);
-        assert_eq!(hist.len(), 5); // 2018, 2019, 2020, 2021, 2022
-        assert_eq!(hist.get(&2018), Some(&1));
-        assert_eq!(hist.get(&2019), Some(&0));
-        assert_eq!(hist.get(&2020), Some(&0));
-        assert_eq!(hist.get(&2021), Some(&0));
-        assert_eq!(hist.get(&2022), Some(&1));
+        let hist = build_histogram(&photos, Granularity::Year);
+        assert_eq!(hist.buckets.len(), 5); // 2018, 2019, 2020, 2021, 2022
+        assert_eq!(hist.buckets.get(&Bucket::Year(2018)), Some(&1));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2019)), Some(&0));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2020)), Some(&0));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2021)), Some(&0));
+        assert_eq!(hist.buckets.get(&Bucket::Year(2022)), Some(&1));
+    }
+
+    #[test]
+    fn test_build_source_histogram_empty() {
+        let photos: Vec<PhotoMeta> = vec![];
+        assert!(build_source_histogram(&photos, Granularity::Year).is_empty());
+    }
+
+    #[test]
+    fn test_build_source_histogram_splits_sources_and_fills_gaps() {
+        let mut modified = make_photo("b.jpg", 2020, 6, 15);
+        modified.source = DateSource::Modify;
+        let photos = vec![
+            make_photo("a.jpg", 2020, 1, 1),
+            modified,
+            make_photo("c.jpg", 2020, 7, 1),
+            make_photo("d.jpg", 2022, 3, 10),
+        ];
+        let hist = build_source_histogram(&photos, Granularity::Year);
+        assert_eq!(hist.buckets.len(), 3);
+        assert_eq!(
+            hist.buckets[&Bucket::Year(2020)].get(&DateSource::Original),
+            Some(&2)
+        );
+        assert_eq!(
+            hist.buckets[&Bucket::Year(2020)].get(&DateSource::Modify),
+            Some(&1)
+        );
+        assert!(hist.buckets[&Bucket::Year(2021)].is_empty());
+        assert_eq!(
+            hist.buckets[&Bucket::Year(2022)].get(&DateSource::Original),
+            Some(&1)
+        );
+    }
+
+    fn labels(hist: &Binned<usize>) -> Vec<(String, usize)> {
+        hist.buckets
+            .iter()
+            .map(|(bucket, &count)| (bucket.to_string(), count))
+            .collect()
+    }
+
+    fn owned(expected: &[(&str, usize)]) -> Vec<(String, usize)> {
+        expected
+            .iter()
+            .map(|(label, count)| (label.to_string(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn test_histogram_by_month_fills_gaps_across_years() {
+        let photos = vec![
+            make_photo("a.jpg", 2022, 11, 5),
+            make_photo("b.jpg", 2023, 2, 1),
+            make_photo("c.jpg", 2023, 2, 28),
+        ];
+        let hist = build_histogram(&photos, Granularity::Month);
+        assert_eq!(
+            labels(&hist),
+            owned(&[
+                ("2022-11", 1),
+                ("2022-12", 0),
+                ("2023-01", 0),
+                ("2023-02", 2)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_histogram_by_quarter_and_day() {
+        let photos = vec![
+            make_photo("a.jpg", 2023, 3, 31),
+            make_photo("b.jpg", 2023, 4, 2),
+        ];
+        assert_eq!(
+            labels(&build_histogram(&photos, Granularity::Quarter)),
+            owned(&[("2023-Q1", 1), ("2023-Q2", 1)])
+        );
+        assert_eq!(
+            labels(&build_histogram(&photos, Granularity::Day)),
+            owned(&[("2023-03-31", 1), ("2023-04-01", 0), ("2023-04-02", 1)])
+        );
+    }
+
+    #[test]
+    fn test_histogram_by_week_uses_iso_weeks() {
+        // 2021-01-01 is a Friday in week 53 of 2020
+        let photos = vec![
+            make_photo("a.jpg", 2020, 12, 24),
+            make_photo("b.jpg", 2021, 1, 1),
+            make_photo("c.jpg", 2021, 1, 4),
+        ];
+        assert_eq!(
+            labels(&build_histogram(&photos, Granularity::Week)),
+            owned(&[("2020-W52", 1), ("2020-W53", 1), ("2021-W01", 1)])
+        );
+    }
+
+    #[test]
+    fn test_histogram_by_hour_and_weekday_cover_the_whole_cycle() {
+        let photos = vec![
+            make_photo_at("a.jpg", 2023, 7, 5, 14, 0, 0), // Wednesday
+            make_photo_at("b.jpg", 2019, 7, 3, 14, 59, 0), // Wednesday
+        ];
+        let hours = build_histogram(&photos, Granularity::Hour);
+        assert_eq!(hours.buckets.len(), 24);
+        assert_eq!(hours.buckets[&Bucket::Hour(14)], 2);
+        assert_eq!(labels(&hours)[0], ("00:00".to_string(), 0));
+
+        let weekdays = build_histogram(&photos, Granularity::Weekday);
+        assert_eq!(
+            labels(&weekdays),
+            owned(&[
+                ("Mon", 0),
+                ("Tue", 0),
+                ("Wed", 2),
+                ("Thu", 0),
+                ("Fri", 0),
+                ("Sat", 0),
+                ("Sun", 0)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_histogram_counts_dates_too_coarse_for_the_unit() {
+        let mut day_only = make_photo("c.jpg", 2023, 5, 1);
+        day_only.precision = Precision::Day;
+        let photos = vec![
+            make_partial("a.jpg", 2023, Precision::Year, true),
+            make_partial("b.jpg", 2023, Precision::Month, false),
+            day_only,
+        ];
+        let by_month = build_histogram(&photos, Granularity::Month);
+        assert_eq!(by_month.unplaced, 1);
+        assert_eq!(
+            labels(&by_month),
+            owned(&[
+                ("2023-01", 1),
+                ("2023-02", 0),
+                ("2023-03", 0),
+                ("2023-04", 0),
+                ("2023-05", 1)
+            ])
+        );
+        let by_hour = build_source_histogram(&photos, Granularity::Hour);
+        assert_eq!(by_hour.unplaced, 3);
+        assert!(by_hour.buckets.is_empty());
+        assert!(!by_hour.is_empty());
+        assert_eq!(build_histogram(&photos, Granularity::Year).unplaced, 0);
+    }
+
+    #[test]
+    fn test_day_counts_keep_the_latest_year() {
+        let photos = vec![
+            make_photo("a.jpg", 2023, 3, 1),
+            make_photo_at("b.jpg", 2023, 3, 1, 18, 0, 0),
+            make_photo("c.jpg", 2022, 12, 31),
+            make_photo("d.jpg", 2023, 12, 31),
+            make_partial("scan.jpg", 2023, Precision::Year, false),
+            make_partial("old.jpg", 1987, Precision::Year, true),
+        ];
+        let days = DayCounts::new(None).fold(photos.clone()).unwrap();
+        assert_eq!(days.year, 2023);
+        assert_eq!(
+            days.days,
+            BTreeMap::from([
+                (NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(), 2),
+                (NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), 1),
+            ])
+        );
+        assert_eq!(days.unplaced, 1);
+        assert_eq!(days.photos(), 3);
+
+        let days = DayCounts::new(Some(2022)).fold(photos.clone()).unwrap();
+        assert_eq!(days.photos(), 1);
+        assert_eq!(days.unplaced, 0);
+
+        let days = DayCounts::new(Some(1987)).fold(photos.clone()).unwrap();
+        assert!(days.days.is_empty());
+        assert_eq!(days.unplaced, 1);
+
+        assert_eq!(DayCounts::new(Some(2000)).fold(photos), None);
+        assert_eq!(DayCounts::default().finish(), None);
+    }
+
+    #[test]
+    fn test_punchcard_counts_weekday_and_hour() {
+        let mut date_only = make_photo("c.jpg", 2023, 7, 5);
+        date_only.precision = Precision::Day;
+        let photos = vec![
+            make_photo_at("a.jpg", 2023, 7, 5, 3, 10, 0), // Wednesday
+            make_photo_at("b.jpg", 2023, 7, 12, 3, 59, 59), // Wednesday
+            make_photo_at("d.jpg", 2023, 7, 9, 23, 0, 0), // Sunday
+            date_only,
+        ];
+        let hours = Punchcard::default().fold(photos).unwrap();
+        assert_eq!(hours.counts[2][3], 2);
+        assert_eq!(hours.counts[6][23], 1);
+        assert_eq!(hours.counts.iter().flatten().sum::<usize>(), 3);
+        assert_eq!(hours.unplaced, 1);
+        assert_eq!(hours.max(), 2);
+        assert_eq!(Punchcard::default().finish(), None);
+    }
+
+    #[test]
+    fn test_stats_summarize_photos_and_skipped_files() {
+        let photos = vec![
+            make_photo("a.jpg", 2023, 3, 1),
+            make_photo_at("b.jpg", 2023, 3, 1, 18, 0, 0),
+            make_photo("c.jpg", 2023, 3, 2),
+            make_photo("d.jpg", 2022, 12, 31),
+            make_partial("old.jpg", 1987, Precision::Year, true),
+        ];
+        let mut stats = Stats::default();
+        stats.skip(SkipReason::NoDate);
+        stats.skip(SkipReason::OutOfRange);
+        stats.skip(SkipReason::NoDate);
+        let summary = stats.fold(photos);
+
+        assert_eq!(summary.scanned, 8);
+        assert_eq!(summary.dated, 5);
+        assert_eq!(
+            summary.skipped,
+            BTreeMap::from([(SkipReason::NoDate, 2), (SkipReason::OutOfRange, 1)])
+        );
+        assert_eq!(
+            summary.oldest.as_ref().unwrap().rel_path,
+            PathBuf::from("old.jpg")
+        );
+        assert_eq!(
+            summary.latest.as_ref().unwrap().rel_path,
+            PathBuf::from("c.jpg")
+        );
+
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            summary.percentiles,
+            vec![
+                (10, day(1987, 1, 1)),
+                (25, day(2022, 12, 31)),
+                (50, day(2023, 3, 1)),
+                (75, day(2023, 3, 1)),
+                (90, day(2023, 3, 2)),
+            ]
+        );
+        assert_eq!(summary.median(), Some(day(2023, 3, 1)));
+
+        // The year-only photo counts towards its year but no month or day
+        assert_eq!(summary.busiest_year, Some((Bucket::Year(2023), 3)));
+        assert_eq!(
+            summary.busiest_month,
+            Some((
+                Bucket::Month {
+                    year: 2023,
+                    month: 3
+                },
+                3
+            ))
+        );
+        assert_eq!(summary.busiest_day, Some((Bucket::Day(day(2023, 3, 1)), 2)));
+        assert_eq!(summary.active_days, 3);
+        assert_eq!(summary.day_photos, 4);
+        assert_eq!(summary.photos_per_active_day(), Some(4.0 / 3.0));
+    }
+
+    #[test]
+    fn test_stats_ties_and_empty_library() {
+        let summary = Stats::default().fold(vec![
+            make_photo("b.jpg", 2021, 5, 2),
+            make_photo("a.jpg", 2020, 5, 1),
+        ]);
+        assert_eq!(summary.busiest_year, Some((Bucket::Year(2020), 1)));
+        assert_eq!(summary.median(), NaiveDate::from_ymd_opt(2020, 5, 1));
+
+        let summary = Stats::default().finish();
+        assert_eq!(summary, Summary::default());
+        assert_eq!(summary.median(), None);
+        assert_eq!(summary.photos_per_active_day(), None);
     }
 }
@@ -0,0 +1,902 @@
+use crate::datetime::Normalizations;
+use crate::error::PtimeError;
+use crate::metadata::{
+    parse_utc_offset, CaptureDate, DateSource, Precision, ScanOptions, SkipReason,
+};
+use crate::scanner::{FoundFile, SidecarNames};
+use crate::takeout;
+use crate::xmp;
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First line of a cache file. Files written by another version of ptime
+/// are discarded, since its parsers may date the same file differently.
+const HEADER: &str = concat!("ptime-cache 2 ", env!("CARGO_PKG_VERSION"));
+/// Starts the lines, right after the header, recording when each options
+/// fingerprint was last scanned with
+const USED: &str = "used\t";
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+/// Files modified this recently are neither looked up nor stored: a second
+/// write within the same timestamp tick (2 s on FAT) would go unnoticed.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+/// A scan that changed nothing still rewrites the cache to record that its
+/// options are in use, but at most this often
+const USE_REFRESH: Duration = Duration::from_secs(24 * 3600);
+/// `prune` drops the entries of options not scanned with for this long
+const UNUSED_AFTER: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Capture dates of previously read files, keyed by absolute path and
+/// valid while the file's size and modification time are unchanged.
+///
+/// Entries are also tied to the options that affect dating (source chain,
+/// patterns, parse mode), so runs with different options share one file
+/// without mixing their results. Only the entries a scan can use, those
+/// below its root read with its options, are held in memory; the others
+/// are copied through when the cache is saved. Lookups and inserts may
+/// come from several worker threads at once.
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    config: u64,
+    /// Canonical scan root
+    root: PathBuf,
+    /// The `dir` source dates files by their path below the scan root, so
+    /// the same file scanned from another root may get another date
+    by_dir: bool,
+    /// Start of the run, since the Unix epoch
+    started: Duration,
+    /// When `config` was last scanned with before this run
+    last_used: Option<Duration>,
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    dirty: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    stamp: Stamp,
+    /// Files with no date are kept too, since they are just as costly to
+    /// re-read
+    capture: Result<CaptureDate, SkipReason>,
+}
+
+/// What a cached date depends on besides the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    size: u64,
+    /// Modification time since the Unix epoch
+    mtime: Duration,
+    /// Hash of the paths, sizes and modification times of existing sidecars
+    sidecars: u64,
+}
+
+/// One line of the cache file: an entry and the scans it belongs to.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    config: u64,
+    /// Hash of the file's directories below the scan root with the `dir`
+    /// source, and 0 without
+    scope: u64,
+    path: PathBuf,
+    entry: Entry,
+}
+
+/// What `open` found at the cache path.
+enum Contents {
+    Missing,
+    /// Written by another version; holds the lines after the header
+    Outdated(Lines<BufReader<fs::File>>),
+    Current(Peekable<Lines<BufReader<fs::File>>>),
+}
+
+/// Default cache file: `$XDG_CACHE_HOME/ptime/cache.tsv`, or
+/// `~/.cache/ptime/cache.tsv`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            PathBuf::from(std::env::var_os("HOME").filter(|home| !home.is_empty())?).join(".cache")
+        }
+    };
+    Some(base.join("ptime").join("cache.tsv"))
+}
+
+/// Deletes the cache file at `path`. Returns whether there was one.
+pub fn clear(path: &Path) -> Result<bool, PtimeError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(PtimeError::Io(e)),
+    }
+}
+
+/// Drops the entries for files that were deleted or changed since they
+/// were cached, or whose sidecars were, whatever options they were read
+/// with, and those of options not scanned with for 30 days. The file is
+/// streamed through, not loaded. Returns how many entries were removed and
+/// how many kept.
+pub fn prune(path: &Path) -> Result<(usize, usize), PtimeError> {
+    let started = now();
+    let (used, lines) = match open(path)? {
+        Contents::Missing => return Ok((0, 0)),
+        Contents::Outdated(lines) => (BTreeMap::new(), lines.peekable()),
+        Contents::Current(mut lines) => (read_used(&mut lines), lines),
+    };
+    let used: BTreeMap<_, _> = used
+        .into_iter()
+        .filter(|(_, last)| *last + UNUSED_AFTER > started)
+        .collect();
+
+    let (mut removed, mut kept) = (0, 0);
+    // Entries of a directory are mostly written together
+    let mut listing: Option<(PathBuf, Option<SidecarNames>)> = None;
+    replace(path, |out| {
+        write_used(out, &used)?;
+        for line in lines {
+            let line = line?;
+            let fresh = parse_line(&line)
+                .filter(|record| used.contains_key(&record.config))
+                .is_some_and(|record| {
+                    let Some(dir) = record.path.parent() else {
+                        return false;
+                    };
+                    if listing.as_ref().is_none_or(|(listed, _)| listed != dir) {
+                        listing = Some((dir.to_path_buf(), SidecarNames::read(dir).ok()));
+                    }
+                    listing
+                        .as_ref()
+                        .and_then(|(_, names)| names.as_ref())
+                        .and_then(|names| stamp(&record.path, names, started))
+                        .is_some_and(|stamp| stamp == record.entry.stamp)
+                });
+            if fresh {
+                writeln!(out, "{line}")?;
+                kept += 1;
+            } else {
+                removed += 1;
+            }
+        }
+        Ok(())
+    })?;
+    Ok((removed, kept))
+}
+
+impl Cache {
+    /// Opens the cache at `path` for a scan of `root` with `options`. A
+    /// missing file, or one written by another version, gives an empty
+    /// cache.
+    pub fn load(path: &Path, options: &ScanOptions, root: &Path) -> Result<Self, PtimeError> {
+        let mut cache = Cache {
+            path: path.to_path_buf(),
+            config: fingerprint(options),
+            // The scan itself reports a root that cannot be resolved
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            by_dir: options.sources.contains(&DateSource::Dir),
+            started: now(),
+            last_used: None,
+            entries: Mutex::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+        };
+        let mut lines = match open(path)? {
+            Contents::Missing => return Ok(cache),
+            Contents::Outdated(_) => {
+                cache.dirty = AtomicBool::new(true);
+                return Ok(cache);
+            }
+            Contents::Current(lines) => lines,
+        };
+
+        cache.last_used = read_used(&mut lines).get(&cache.config).copied();
+        let mut entries = HashMap::new();
+        for line in lines {
+            // Damaged lines are dropped and rebuilt on the next scan
+            if let Some(record) = parse_line(&line?).filter(|record| cache.owns(record)) {
+                entries.insert(record.path, record.entry);
+            }
+        }
+        cache.entries = Mutex::new(entries);
+        Ok(cache)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of entries for this scan.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached date of `found` when it is still valid, and
+    /// otherwise calls `read` and remembers its result.
+    pub fn get_or_read(
+        &self,
+        found: &FoundFile,
+        read: impl FnOnce() -> Result<Result<CaptureDate, SkipReason>, PtimeError>,
+    ) -> Result<Result<CaptureDate, SkipReason>, PtimeError> {
+        // Files that cannot be stamped are read every time
+        let Some(stamp) = stamp(&found.abs_path, &found.sidecars, self.started) else {
+            return read();
+        };
+        if let Some(entry) = self.entries.lock().unwrap().get(&found.abs_path) {
+            if entry.stamp == stamp {
+                return Ok(entry.capture);
+            }
+        }
+
+        let capture = read()?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(found.abs_path.clone(), Entry { stamp, capture });
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(capture)
+    }
+
+    /// Writes the cache back if anything changed, streaming the entries of
+    /// other scans through from the current file. The file is replaced
+    /// atomically, so a concurrent run never reads a half-written cache.
+    pub fn save(&self) -> Result<(), PtimeError> {
+        let entries = self.entries.lock().unwrap();
+        let refresh = !entries.is_empty()
+            && self
+                .last_used
+                .is_none_or(|used| used + USE_REFRESH <= self.started);
+        if !self.dirty.load(Ordering::Relaxed) && !refresh {
+            return Ok(());
+        }
+
+        let (mut used, lines) = match open(&self.path)? {
+            Contents::Current(mut lines) => (read_used(&mut lines), Some(lines)),
+            Contents::Missing | Contents::Outdated(_) => (BTreeMap::new(), None),
+        };
+        used.insert(self.config, self.started);
+        // In path order, so that `prune` sees each directory's entries
+        // together
+        let mut own: Vec<_> = entries.iter().collect();
+        own.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        replace(&self.path, |out| {
+            write_used(out, &used)?;
+            for line in lines.into_iter().flatten() {
+                let line = line?;
+                // Entries of this scan are written from memory below
+                if parse_line(&line).is_some_and(|record| !self.owns(&record)) {
+                    writeln!(out, "{line}")?;
+                }
+            }
+            for (path, entry) in own {
+                let Ok(rel_path) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let record = Record {
+                    config: self.config,
+                    scope: self.scope(rel_path),
+                    path: path.clone(),
+                    entry: *entry,
+                };
+                if let Some(line) = format_line(&record) {
+                    writeln!(out, "{line}")?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Whether `record` is one this scan may use: below its root and read
+    /// with its options.
+    fn owns(&self, record: &Record) -> bool {
+        record.config == self.config
+            && record
+                .path
+                .strip_prefix(&self.root)
+                .is_ok_and(|rel_path| record.scope == self.scope(rel_path))
+    }
+
+    /// Scope of the entry for the file at `rel_path` below the scan root.
+    fn scope(&self, rel_path: &Path) -> u64 {
+        if !self.by_dir {
+            return 0;
+        }
+        let mut hasher = Fnv::default();
+        rel_path.parent().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn open(path: &Path) -> Result<Contents, PtimeError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Contents::Missing),
+        Err(e) => return Err(PtimeError::Io(e)),
+    };
+    let mut lines = BufReader::new(file).lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Ok(Contents::Outdated(lines));
+    }
+    Ok(Contents::Current(lines.peekable()))
+}
+
+/// Reads the last use of each options fingerprint, leaving `lines` at the
+/// first entry.
+fn read_used(lines: &mut Peekable<Lines<BufReader<fs::File>>>) -> BTreeMap<u64, Duration> {
+    let mut used = BTreeMap::new();
+    while let Some(Ok(line)) = lines.peek() {
+        let Some(fields) = line.strip_prefix(USED) else {
+            break;
+        };
+        let parsed = fields.split_once('\t').and_then(|(config, secs)| {
+            Some((
+                u64::from_str_radix(config, 16).ok()?,
+                Duration::from_secs(secs.parse().ok()?),
+            ))
+        });
+        if let Some((config, last)) = parsed {
+            used.insert(config, last);
+        }
+        lines.next();
+    }
+    used
+}
+
+/// Writes the header and the last use of each options fingerprint.
+fn write_used(out: &mut impl Write, used: &BTreeMap<u64, Duration>) -> io::Result<()> {
+    writeln!(out, "{HEADER}")?;
+    for (config, last) in used {
+        writeln!(out, "{USED}{config:016x}\t{}", last.as_secs())?;
+    }
+    Ok(())
+}
+
+/// Replaces the file at `path` with what `write` produces, through a
+/// temporary file renamed over it.
+fn replace(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<fs::File>) -> io::Result<()>,
+) -> Result<(), PtimeError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut temp = path.to_path_buf().into_os_string();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    let temp = PathBuf::from(temp);
+    let written = (|| {
+        let mut out = BufWriter::new(fs::File::create(&temp)?);
+        write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(written?)
+}
+
+/// Covers XMP and Takeout sidecars whatever the source chain, so that
+/// `prune` can check entries of every option set. Sidecars not in
+/// `listing`, the file's directory, are not stat'ed. Files modified within
+/// the racy window of `started` get no stamp.
+fn stamp(path: &Path, listing: &SidecarNames, started: Duration) -> Option<Stamp> {
+    let is_racy = |mtime: Duration| mtime + RACY_WINDOW > started;
+    let (size, mtime) = file_stamp(path)?;
+    let mut hasher = Fnv::default();
+    let sidecars = xmp::sidecar_paths(path)
+        .into_iter()
+        .chain(takeout::sidecar_paths(path))
+        .filter(|sidecar| listing.contains(sidecar));
+    for sidecar in sidecars {
+        if let Some(sidecar_stamp) = file_stamp(&sidecar) {
+            (sidecar, sidecar_stamp).hash(&mut hasher);
+            if is_racy(sidecar_stamp.1) {
+                return None;
+            }
+        }
+    }
+    if is_racy(mtime) {
+        return None;
+    }
+    Some(Stamp {
+        size,
+        mtime,
+        sidecars: hasher.finish(),
+    })
+}
+/// Size and modification time of a regular file. Files modified before the
+/// epoch are left uncached.
+fn file_stamp(path: &Path) -> Option<(u64, Duration)> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), mtime))
+}
+
+/// Identifies the options that decide which date a file gets. Media
+/// filters and job counts only change which files are read, or how fast.
+fn fingerprint(options: &ScanOptions) -> u64 {
+    let mut hasher = Fnv::default();
+    for source in &options.sources {
+        source.to_string().hash(&mut hasher);
+    }
+    for pattern in &options.filename_patterns {
+        ("filename", pattern.to_string()).hash(&mut hasher);
+    }
+    for pattern in &options.dir_patterns {
+        ("dir", pattern.to_string()).hash(&mut hasher);
+    }
+    format!("{:?}", options.parse_mode).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// FNV-1a: unlike the standard library's hasher, stable across builds,
+/// which matters for values written to disk.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn precision_name(precision: Precision) -> &'static str {
+    match precision {
+        Precision::Year => "year",
+        Precision::Month => "month",
+        Precision::Day => "day",
+        Precision::Time => "time",
+    }
+}
+
+fn parse_precision(name: &str) -> Option<Precision> {
+    match name {
+        "year" => Some(Precision::Year),
+        "month" => Some(Precision::Month),
+        "day" => Some(Precision::Day),
+        "time" => Some(Precision::Time),
+        _ => None,
+    }
+}
+
+/// One tab-separated line: config, scope, size, mtime, sidecars, then either the
+/// capture date fields or a single `-` (no date) or `!` (unreadable
+/// metadata), and the escaped path last. Paths that are not valid UTF-8 are
+/// not written.
+fn format_line(record: &Record) -> Option<String> {
+    let Record {
+        config,
+        scope,
+        ref path,
+        entry,
+    } = *record;
+    let path = escape(path.to_str()?);
+    let Stamp {
+        size,
+        mtime,
+        sidecars,
+    } = entry.stamp;
+    let stamp = format!(
+        "{config:016x}\t{scope:016x}\t{size}\t{}.{:09}\t{sidecars:016x}",
+        mtime.as_secs(),
+        mtime.subsec_nanos()
+    );
+    let capture = match entry.capture {
+        Err(SkipReason::Unreadable) => "!".to_string(),
+        Err(_) => "-".to_string(),
+        Ok(capture) => format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            capture.datetime.format(DATETIME_FORMAT),
+            capture
+                .offset
+                .map_or_else(|| "-".to_string(), |offset| offset.to_string()),
+            capture.source,
+            precision_name(capture.precision),
+            u8::from(capture.approximate),
+            capture.normalized.bits(),
+        ),
+    };
+    Some(format!("{stamp}\t{capture}\t{path}"))
+}
+
+fn parse_line(line: &str) -> Option<Record> {
+    let mut fields = line.split('\t');
+    let config = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let scope = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let size = fields.next()?.parse().ok()?;
+    let (secs, nanos) = fields.next()?.split_once('.')?;
+    let mtime = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    let sidecars = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+    let capture = match fields.next()? {
+        "-" => Err(SkipReason::NoDate),
+        "!" => Err(SkipReason::Unreadable),
+        datetime => Ok(CaptureDate {
+            datetime: NaiveDateTime::parse_from_str(datetime, DATETIME_FORMAT).ok()?,
+            offset: match fields.next()? {
+                "-" => None,
+                offset => Some(parse_utc_offset(offset)?),
+            },
+            source: DateSource::from_str(fields.next()?, false).ok()?,
+            precision: parse_precision(fields.next()?)?,
+            approximate: match fields.next()? {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            },
+            normalized: Normalizations::from_bits(fields.next()?.parse().ok()?),
+        }),
+    };
+    let path = PathBuf::from(unescape(fields.next()?)?);
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let stamp = Stamp {
+        size,
+        mtime,
+        sidecars,
+    };
+    Some(Record {
+        config,
+        scope,
+        path,
+        entry: Entry { stamp, capture },
+    })
+}
+
+/// Escapes the characters that would break the line format.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::Normalization;
+    use crate::format::FileFormat;
+    use chrono::{FixedOffset, NaiveDate};
+    use std::cell::Cell;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn capture() -> CaptureDate {
+        let mut normalized = Normalizations::default();
+        normalized.insert(Normalization::Separator);
+        CaptureDate {
+            datetime: NaiveDate::from_ymd_opt(2019, 7, 15)
+                .unwrap()
+                .and_hms_nano_opt(10, 11, 12, 260_000_000)
+                .unwrap(),
+            offset: FixedOffset::east_opt(9 * 3600),
+            source: DateSource::Original,
+            precision: Precision::Time,
+            approximate: false,
+            normalized,
+        }
+    }
+
+    /// Writes a file last modified an hour ago, outside the racy window.
+    fn old_file(path: &Path, contents: &[u8]) {
+        fs::write(path, contents).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+    }
+
+    /// Reads `path` through the cache, counting calls to the reader.
+    fn read(cache: &Cache, path: &Path, reads: &Cell<u32>) -> Option<CaptureDate> {
+        let found = FoundFile {
+            rel_path: PathBuf::from(path.file_name().unwrap()),
+            abs_path: path.to_path_buf(),
+            format: FileFormat::Jpeg,
+            sidecars: Arc::new(SidecarNames::read(path.parent().unwrap()).unwrap()),
+        };
+        cache
+            .get_or_read(&found, || {
+                reads.set(reads.get() + 1);
+                Ok(Ok(capture()))
+            })
+            .unwrap()
+            .ok()
+    }
+
+    #[test]
+    fn test_unchanged_file_is_read_once() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache_path = temp.path().join("cache.tsv");
+        let options = ScanOptions::default();
+        let reads = Cell::new(0);
+
+        let cache = Cache::load(&cache_path, &options, temp.path()).unwrap();
+        assert_eq!(read(&cache, &photo, &reads), Some(capture()));
+        assert_eq!(read(&cache, &photo, &reads), Some(capture()));
+        assert_eq!(reads.get(), 1);
+        cache.save().unwrap();
+
+        let cache = Cache::load(&cache_path, &options, temp.path()).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(read(&cache, &photo, &reads), Some(capture()));
+        assert_eq!(reads.get(), 1);
+    }
+
+    #[test]
+    fn test_changed_file_is_read_again() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache = Cache::load(
+            &temp.path().join("cache.tsv"),
+            &ScanOptions::default(),
+            temp.path(),
+        )
+        .unwrap();
+        let reads = Cell::new(0);
+
+        read(&cache, &photo, &reads);
+        old_file(&photo, b"edited jpeg");
+        read(&cache, &photo, &reads);
+        assert_eq!(reads.get(), 2);
+    }
+
+    #[test]
+    fn test_new_sidecar_invalidates_entry() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache = Cache::load(
+            &temp.path().join("cache.tsv"),
+            &ScanOptions::default(),
+            temp.path(),
+        )
+        .unwrap();
+        let reads = Cell::new(0);
+
+        read(&cache, &photo, &reads);
+        old_file(&temp.path().join("photo.xmp"), b"<x:xmpmeta/>");
+        read(&cache, &photo, &reads);
+        read(&cache, &photo, &reads);
+        assert_eq!(reads.get(), 2);
+    }
+
+    #[test]
+    fn test_recently_modified_file_is_not_cached() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        fs::write(&photo, b"jpeg").unwrap();
+        let cache = Cache::load(
+            &temp.path().join("cache.tsv"),
+            &ScanOptions::default(),
+            temp.path(),
+        )
+        .unwrap();
+        let reads = Cell::new(0);
+
+        read(&cache, &photo, &reads);
+        read(&cache, &photo, &reads);
+        assert_eq!(reads.get(), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_entries_are_kept_per_option_set() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache_path = temp.path().join("cache.tsv");
+        let gps_only = ScanOptions::with_sources(&[DateSource::Gps]);
+        let reads = Cell::new(0);
+
+        for options in [&ScanOptions::default(), &gps_only, &ScanOptions::default()] {
+            let cache = Cache::load(&cache_path, options, temp.path()).unwrap();
+            assert!(cache.len() <= 1);
+            read(&cache, &photo, &reads);
+            cache.save().unwrap();
+        }
+        assert_eq!(reads.get(), 2);
+    }
+
+    #[test]
+    fn test_only_entries_below_the_root_are_loaded() {
+        let temp = tempdir().unwrap();
+        let cache_path = temp.path().join("cache.tsv");
+        let options = ScanOptions::default();
+        let reads = Cell::new(0);
+        let mut photos = Vec::new();
+        for dir in ["a", "b"] {
+            let root = temp.path().join(dir);
+            fs::create_dir(&root).unwrap();
+            let photo = root.join("photo.jpg");
+            old_file(&photo, b"jpeg");
+            let cache = Cache::load(&cache_path, &options, &root).unwrap();
+            assert!(cache.is_empty());
+            read(&cache, &photo, &reads);
+            cache.save().unwrap();
+            photos.push((root, photo));
+        }
+
+        for (root, photo) in &photos {
+            let cache = Cache::load(&cache_path, &options, root).unwrap();
+            assert_eq!(cache.len(), 1);
+            read(&cache, photo, &reads);
+        }
+        assert_eq!(reads.get(), 2);
+        let cache = Cache::load(&cache_path, &options, temp.path()).unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_undated_files_and_odd_paths_round_trip() {
+        let entry = Entry {
+            stamp: Stamp {
+                size: 42,
+                mtime: Duration::new(1_563_185_472, 5),
+                sidecars: 7,
+            },
+            capture: Err(SkipReason::NoDate),
+        };
+        let record = Record {
+            config: 3,
+            scope: 4,
+            path: PathBuf::from("/photos/tab\there\\new\nline.jpg"),
+            entry,
+        };
+        let line = format_line(&record).unwrap();
+        assert!(!line.contains('\n'));
+        assert_eq!(parse_line(&line), Some(record.clone()));
+
+        for capture in [Ok(capture()), Err(SkipReason::Unreadable)] {
+            let record = Record {
+                entry: Entry { capture, ..entry },
+                ..record.clone()
+            };
+            assert_eq!(parse_line(&format_line(&record).unwrap()), Some(record));
+        }
+    }
+
+    #[test]
+    fn test_cache_from_other_version_is_discarded() {
+        let temp = tempdir().unwrap();
+        let cache_path = temp.path().join("cache.tsv");
+        fs::write(&cache_path, "ptime-cache 0 0.0.0\nsomething\n").unwrap();
+
+        let cache = Cache::load(&cache_path, &ScanOptions::default(), temp.path()).unwrap();
+        assert!(cache.is_empty());
+        cache.save().unwrap();
+        let saved = fs::read_to_string(&cache_path).unwrap();
+        assert_eq!(saved.lines().next(), Some(HEADER));
+        assert!(!saved.contains("something"));
+    }
+
+    #[test]
+    fn test_damaged_lines_are_skipped() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache_path = temp.path().join("cache.tsv");
+        let cache = Cache::load(&cache_path, &ScanOptions::default(), temp.path()).unwrap();
+        read(&cache, &photo, &Cell::new(0));
+        cache.save().unwrap();
+
+        let mut contents = fs::read_to_string(&cache_path).unwrap();
+        contents.push_str("not\ta\tcache\tline\n");
+        fs::write(&cache_path, contents).unwrap();
+        let cache = Cache::load(&cache_path, &ScanOptions::default(), temp.path()).unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_prune_drops_deleted_and_changed_files() {
+        let temp = tempdir().unwrap();
+        let kept = temp.path().join("kept.jpg");
+        let deleted = temp.path().join("deleted.jpg");
+        let changed = temp.path().join("changed.jpg");
+        let retagged = temp.path().join("retagged.jpg");
+        for path in [&kept, &deleted, &changed, &retagged] {
+            old_file(path, b"jpeg");
+        }
+        let cache_path = temp.path().join("cache.tsv");
+        let cache = Cache::load(&cache_path, &ScanOptions::default(), temp.path()).unwrap();
+        for path in [&kept, &deleted, &changed, &retagged] {
+            read(&cache, path, &Cell::new(0));
+        }
+        cache.save().unwrap();
+
+        fs::remove_file(&deleted).unwrap();
+        old_file(&changed, b"edited jpeg");
+        old_file(&temp.path().join("retagged.jpg.xmp"), b"<x:xmpmeta/>");
+        assert_eq!(prune(&cache_path).unwrap(), (3, 1));
+        let cache = Cache::load(&cache_path, &ScanOptions::default(), temp.path()).unwrap();
+        assert_eq!(cache.len(), 1);
+
+        assert!(clear(&cache_path).unwrap());
+        assert!(!clear(&cache_path).unwrap());
+    }
+
+    #[test]
+    fn test_prune_drops_unused_option_sets() {
+        let temp = tempdir().unwrap();
+        let photo = temp.path().join("photo.jpg");
+        old_file(&photo, b"jpeg");
+        let cache_path = temp.path().join("cache.tsv");
+        let gps_only = ScanOptions::with_sources(&[DateSource::Gps]);
+        for options in [&ScanOptions::default(), &gps_only] {
+            let cache = Cache::load(&cache_path, options, temp.path()).unwrap();
+            read(&cache, &photo, &Cell::new(0));
+            cache.save().unwrap();
+        }
+
+        let contents = fs::read_to_string(&cache_path).unwrap();
+        let gps_used = format!("{USED}{:016x}\t", fingerprint(&gps_only));
+        let contents: String = contents
+            .lines()
+            .map(|line| match line.strip_prefix(&gps_used) {
+                Some(_) => format!("{gps_used}0\n"),
+                None => format!("{line}\n"),
+            })
+            .collect();
+        fs::write(&cache_path, contents).unwrap();
+        assert_eq!(prune(&cache_path).unwrap(), (1, 1));
+        assert_eq!(prune(&cache_path).unwrap(), (0, 1));
+        let cache = Cache::load(&cache_path, &gps_only, temp.path()).unwrap();
+        assert!(cache.is_empty());
+    }
+}
@@ -1,40 +1,182 @@
-use clap::{Parser, Subcommand};
+use crate::analysis::Granularity;
+use crate::cache;
+use crate::datetime::ParseMode;
+use crate::directory::DirPattern;
+use crate::filename::FilenamePattern;
+use crate::filter::{DateBound, DateRange};
+use crate::format::MediaFilter;
+use crate::metadata::{parse_utc_offset, DateSource, ScanOptions, TargetZone};
+use crate::render::PhotoFormat;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "ptime")]
-#[command(about = "Analyze photo timestamps from JPEG files")]
+#[command(
+    about = "Analyze photo timestamps from JPEG, HEIF, PNG, WebP, TIFF, RAW and MP4/MOV/3GP files"
+)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+/// Options shared by every subcommand
+#[derive(Args, Debug, Default)]
+pub struct GlobalArgs {
+    /// Normalize capture times to a time zone: utc, local, or an offset such as +09:00
+    #[arg(long, global = true, allow_hyphen_values = true, value_parser = parse_tz)]
+    pub tz: Option<TargetZone>,
+    /// Date sources to try, in priority order (e.g. original,digitized,modify,gps)
+    #[arg(long = "date-source", global = true, value_enum, value_delimiter = ',')]
+    pub date_source: Vec<DateSource>,
+    /// Prefer the GPS date/time (UTC) over the camera clock when present
+    #[arg(long, global = true)]
+    pub gps: bool,
+    /// Kinds of media to scan [default: all]
+    #[arg(long, global = true, value_enum)]
+    pub media: Option<MediaFilter>,
+    /// Extra file name pattern for the filename source: a regex with named
+    /// groups year, month, day (hour, minute, second optional) or a strftime
+    /// template such as %Y%m%d_%H%M%S
+    #[arg(long = "filename-pattern", global = true, value_parser = FilenamePattern::parse)]
+    pub filename_pattern: Vec<FilenamePattern>,
+    /// Extra pattern for the dir source, matched against the relative
+    /// directory path: a regex with a named group year and optional month, day
+    #[arg(long = "dir-pattern", global = true, value_parser = DirPattern::parse)]
+    pub dir_pattern: Vec<DirPattern>,
+    /// Last-resort file system timestamp for files without any embedded date;
+    /// such dates are flagged as low confidence
+    #[arg(long, global = true, value_enum)]
+    pub fallback: Option<Fallback>,
+    /// How to treat malformed EXIF dates such as 2019/07/15 or
+    /// 2019-07-15T10:11:12Z: repair them (lenient) or skip them (strict)
+    /// [default: lenient]
+    #[arg(long = "date-parsing", global = true, value_enum)]
+    pub date_parsing: Option<ParseMode>,
+    /// Number of files read in parallel [default: number of CPUs]
+    #[arg(short, long, global = true)]
+    pub jobs: Option<NonZeroUsize>,
+    /// Cache of capture dates, reused while a file's size and modification
+    /// time are unchanged [default: ~/.cache/ptime/cache.tsv]
+    #[arg(long, global = true, value_name = "FILE")]
+    pub cache: Option<PathBuf>,
+    /// Read every file, neither using nor updating the cache
+    #[arg(long = "no-cache", global = true, conflicts_with = "cache")]
+    pub no_cache: bool,
+    /// Only photos taken in or after this period: 2019, 2019-07, 2019-07-15,
+    /// or an age such as 30d, 6w, 3m, 5y
+    #[arg(long, global = true, value_name = "DATE", value_parser = DateBound::parse)]
+    pub since: Option<DateBound>,
+    /// Only photos taken in or before this period, in the same forms as
+    /// --since; --until 2019 includes all of 2019
+    #[arg(long, global = true, value_name = "DATE", value_parser = DateBound::parse)]
+    pub until: Option<DateBound>,
+}
+
+/// File system timestamps accepted by `--fallback`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Fallback {
+    /// Last modification time
+    Mtime,
+    /// Creation (birth) time, where the file system records it
+    Btime,
+}
+
+impl Fallback {
+    fn source(self) -> DateSource {
+        match self {
+            Fallback::Mtime => DateSource::Mtime,
+            Fallback::Btime => DateSource::Btime,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Find the oldest photo
     Oldest {
+        /// Print the full capture timestamp instead of the date only
+        #[arg(short, long)]
+        timestamp: bool,
+        /// Show which metadata source the date was read from
+        #[arg(long)]
+        show_source: bool,
         /// Directory to scan (default: current directory)
         directory: Option<PathBuf>,
     },
     /// Find the most recent photo
     Latest {
+        /// Print the full capture timestamp instead of the date only
+        #[arg(short, long)]
+        timestamp: bool,
+        /// Show which metadata source the date was read from
+        #[arg(long)]
+        show_source: bool,
         /// Directory to scan (default: current directory)
         directory: Option<PathBuf>,
     },
-    /// Show histogram of photos by year
+    /// Show histogram of photos by year, or by a finer unit with --by
     Hist {
         /// Width of histogram bars (1-200, clamped at 200)
         #[arg(short, long, default_value_t = 50, value_parser = parse_hist_width)]
         width: usize,
+        /// Stack each bar by the metadata source the dates were read from
+        #[arg(long)]
+        by_source: bool,
+        /// Unit each bar counts photos by; hour and weekday add up all dates
+        #[arg(long, value_enum, default_value_t = Granularity::Year)]
+        by: Granularity,
         /// Directory to scan (default: current directory)
         directory: Option<PathBuf>,
     },
+    /// Show a year as a calendar heatmap of photos per day
+    Calendar {
+        /// Year to show (default: the latest year with photos)
+        #[arg(long)]
+        year: Option<i32>,
+        /// Directory to scan (default: current directory)
+        directory: Option<PathBuf>,
+    },
+    /// Show photos per hour of the day for each weekday
+    Punchcard {
+        /// Directory to scan (default: current directory)
+        directory: Option<PathBuf>,
+    },
+    /// Summarize the library: counts, date range, percentiles and peaks
+    Stats {
+        /// Directory to scan (default: current directory)
+        directory: Option<PathBuf>,
+    },
+    /// Maintain the cache of capture dates
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+/// What `ptime cache` does to the cache file
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheAction {
+    /// Remove entries for files that were deleted or changed, or for
+    /// options not used for 30 days
+    Prune,
+    /// Delete the cache file
+    Clear,
 }
 
 #[derive(Debug)]
 pub struct CliCommand {
     pub kind: CommandKind,
     pub directory: PathBuf,
+    pub tz: Option<TargetZone>,
+    pub options: ScanOptions,
+    /// Cache file to use, unless disabled with `--no-cache`
+    pub cache: Option<PathBuf>,
+    /// Capture times kept by `--since`/`--until`
+    pub range: DateRange,
 }
 
 fn parse_hist_width(value: &str) -> Result<usize, String> {
@@ -48,11 +190,35 @@ fn parse_hist_width(value: &str) -> Result<usize, String> {
     }
 }
 
+fn parse_tz(value: &str) -> Result<TargetZone, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "utc" | "z" => Ok(TargetZone::Utc),
+        "local" => Ok(TargetZone::Local),
+        _ => parse_utc_offset(value)
+            .map(TargetZone::Fixed)
+            .ok_or_else(|| format!("Invalid time zone: {value} (expected utc, local or +HH:MM)")),
+    }
+}
+
 #[derive(Debug)]
 pub enum CommandKind {
-    Oldest,
-    Latest,
-    Hist { width: usize },
+    Oldest {
+        format: PhotoFormat,
+    },
+    Latest {
+        format: PhotoFormat,
+    },
+    Hist {
+        width: usize,
+        by_source: bool,
+        by: Granularity,
+    },
+    Calendar {
+        year: Option<i32>,
+    },
+    Punchcard,
+    Stats,
+    Cache(CacheAction),
 }
 
 impl Cli {
@@ -63,77 +229,472 @@ impl Cli {
 
     fn convert(cli: Cli) -> Result<CliCommand, String> {
         let (kind, directory) = match cli.command {
-            Command::Oldest { directory } => {
+            Command::Oldest {
+                timestamp,
+                show_source,
+                directory,
+            } => {
                 let dir = directory.unwrap_or_else(|| PathBuf::from("."));
-                (CommandKind::Oldest, dir)
+                let format = PhotoFormat {
+                    timestamp,
+                    source: show_source,
+                };
+                (CommandKind::Oldest { format }, dir)
             }
-            Command::Latest { directory } => {
+            Command::Latest {
+                timestamp,
+                show_source,
+                directory,
+            } => {
                 let dir = directory.unwrap_or_else(|| PathBuf::from("."));
-                (CommandKind::Latest, dir)
+                let format = PhotoFormat {
+                    timestamp,
+                    source: show_source,
+                };
+                (CommandKind::Latest { format }, dir)
             }
-            Command::Hist { width, directory } => {
+            Command::Hist {
+                width,
+                by_source,
+                by,
+                directory,
+            } => {
                 let clamped_width = width.min(200);
                 let dir = directory.unwrap_or_else(|| PathBuf::from("."));
                 (
                     CommandKind::Hist {
                         width: clamped_width,
+                        by_source,
+                        by,
                     },
                     dir,
                 )
             }
+            Command::Calendar { year, directory } => {
+                let dir = directory.unwrap_or_else(|| PathBuf::from("."));
+                (CommandKind::Calendar { year }, dir)
+            }
+            Command::Punchcard { directory } => (
+                CommandKind::Punchcard,
+                directory.unwrap_or_else(|| PathBuf::from(".")),
+            ),
+            Command::Stats { directory } => (
+                CommandKind::Stats,
+                directory.unwrap_or_else(|| PathBuf::from(".")),
+            ),
+            Command::Cache { action } => (CommandKind::Cache(action), PathBuf::from(".")),
         };
 
-        Ok(CliCommand { kind, directory })
+        let cache = if cli.global.no_cache {
+            None
+        } else {
+            cli.global.cache.or_else(cache::default_path)
+        };
+        if cache.is_none() && matches!(kind, CommandKind::Cache(_)) {
+            return Err(if cli.global.no_cache {
+                "The cache command cannot be combined with --no-cache".to_string()
+            } else {
+                "No cache location: pass --cache FILE or set HOME".to_string()
+            });
+        }
+
+        let mut options = ScanOptions::with_sources(&cli.global.date_source);
+        if cli.global.gps {
+            options.prefer(DateSource::Gps);
+        }
+        if let Some(fallback) = cli.global.fallback {
+            let source = fallback.source();
+            if !options.sources.contains(&source) {
+                options.sources.push(source);
+            }
+        }
+        options.media = cli.global.media.unwrap_or_default();
+        options.filename_patterns = cli.global.filename_pattern;
+        options.dir_patterns = cli.global.dir_pattern;
+        options.parse_mode = cli.global.date_parsing.unwrap_or_default();
+        if let Some(jobs) = cli.global.jobs {
+            options.jobs = jobs;
+        }
+
+        // Relative bounds count back from now in the zone photos end up in
+        let now = cli.global.tz.unwrap_or(TargetZone::Local).now();
+        let range = DateRange::new(cli.global.since, cli.global.until, now)?;
+
+        Ok(CliCommand {
+            kind,
+            directory,
+            tz: cli.global.tz,
+            options,
+            cache,
+            range,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata::DEFAULT_SOURCES;
 
     #[test]
     fn test_oldest_with_default_dir() {
         let cli = Cli {
-            command: Command::Oldest { directory: None },
+            global: GlobalArgs::default(),
+            command: Command::Oldest {
+                timestamp: false,
+                show_source: false,
+                directory: None,
+            },
         };
         let result = Cli::convert(cli).unwrap();
-        assert!(matches!(result.kind, CommandKind::Oldest));
+        assert!(matches!(
+            result.kind,
+            CommandKind::Oldest { format } if format == PhotoFormat::default()
+        ));
         assert_eq!(result.directory, PathBuf::from("."));
     }
 
     #[test]
     fn test_oldest_with_custom_dir() {
         let cli = Cli {
+            global: GlobalArgs::default(),
             command: Command::Oldest {
+                timestamp: false,
+                show_source: false,
                 directory: Some(PathBuf::from("/tmp/photos")),
             },
         };
         let result = Cli::convert(cli).unwrap();
-        assert!(matches!(result.kind, CommandKind::Oldest));
+        assert!(matches!(
+            result.kind,
+            CommandKind::Oldest { format } if format == PhotoFormat::default()
+        ));
         assert_eq!(result.directory, PathBuf::from("/tmp/photos"));
     }
 
     #[test]
     fn test_latest_with_default_dir() {
         let cli = Cli {
-            command: Command::Latest { directory: None },
+            global: GlobalArgs::default(),
+            command: Command::Latest {
+                timestamp: false,
+                show_source: false,
+                directory: None,
+            },
         };
         let result = Cli::convert(cli).unwrap();
-        assert!(matches!(result.kind, CommandKind::Latest));
+        assert!(matches!(
+            result.kind,
+            CommandKind::Latest { format } if format == PhotoFormat::default()
+        ));
         assert_eq!(result.directory, PathBuf::from("."));
     }
 
+    #[test]
+    fn test_latest_with_timestamp_flag() {
+        let cli = Cli::try_parse_from(["ptime", "latest", "--timestamp"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Latest { format } if format.timestamp && !format.source
+        ));
+    }
+
+    #[test]
+    fn test_tz_defaults_to_none() {
+        let cli = Cli::try_parse_from(["ptime", "oldest"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert_eq!(result.tz, None);
+    }
+
+    #[test]
+    fn test_tz_accepts_utc_local_and_offsets() {
+        let cli = Cli::try_parse_from(["ptime", "hist", "--tz", "UTC"]).unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().tz, Some(TargetZone::Utc));
+
+        let cli = Cli::try_parse_from(["ptime", "--tz", "local", "latest"]).unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().tz, Some(TargetZone::Local));
+
+        let cli = Cli::try_parse_from(["ptime", "oldest", "--tz", "+09:00"]).unwrap();
+        assert_eq!(
+            Cli::convert(cli).unwrap().tz,
+            Some(TargetZone::Fixed(
+                chrono::FixedOffset::east_opt(9 * 3600).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tz_invalid_errors() {
+        let result = Cli::try_parse_from(["ptime", "oldest", "--tz", "Mars/Olympus"]);
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn test_gps_flag_puts_gps_first() {
+        let cli = Cli::try_parse_from(["ptime", "oldest"]).unwrap();
+        assert!(!Cli::convert(cli)
+            .unwrap()
+            .options
+            .sources
+            .contains(&DateSource::Gps));
+
+        let cli = Cli::try_parse_from(["ptime", "oldest", "--gps"]).unwrap();
+        let sources = Cli::convert(cli).unwrap().options.sources;
+        assert_eq!(sources[0], DateSource::Gps);
+        assert_eq!(sources.len(), DEFAULT_SOURCES.len() + 1);
+    }
+
+    #[test]
+    fn test_media_defaults_to_all() {
+        let cli = Cli::try_parse_from(["ptime", "oldest"]).unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().options.media, MediaFilter::All);
+
+        let cli = Cli::try_parse_from(["ptime", "hist", "--media", "photos"]).unwrap();
+        assert_eq!(
+            Cli::convert(cli).unwrap().options.media,
+            MediaFilter::Photos
+        );
+
+        let cli = Cli::try_parse_from(["ptime", "--media", "videos", "latest"]).unwrap();
+        assert_eq!(
+            Cli::convert(cli).unwrap().options.media,
+            MediaFilter::Videos
+        );
+    }
+
+    #[test]
+    fn test_date_source_list_replaces_default_chain() {
+        let cli =
+            Cli::try_parse_from(["ptime", "hist", "--date-source", "original,modify"]).unwrap();
+        assert_eq!(
+            Cli::convert(cli).unwrap().options.sources,
+            vec![DateSource::Original, DateSource::Modify]
+        );
+    }
+
+    #[test]
+    fn test_date_source_is_repeatable() {
+        let cli = Cli::try_parse_from([
+            "ptime",
+            "oldest",
+            "--date-source",
+            "gps",
+            "--date-source",
+            "digitized,original",
+        ])
+        .unwrap();
+        assert_eq!(
+            Cli::convert(cli).unwrap().options.sources,
+            vec![DateSource::Gps, DateSource::Digitized, DateSource::Original]
+        );
+    }
+
+    #[test]
+    fn test_date_source_unknown_errors() {
+        let result = Cli::try_parse_from(["ptime", "oldest", "--date-source", "sundial"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::InvalidValue
+        );
+    }
+
+    #[test]
+    fn test_filename_pattern_is_repeatable() {
+        let cli = Cli::try_parse_from([
+            "ptime",
+            "oldest",
+            "--filename-pattern",
+            "%d%m%Y",
+            "--filename-pattern",
+            r"(?P<year>\d{4})x(?P<month>\d{2})x(?P<day>\d{2})",
+        ])
+        .unwrap();
+        let patterns = Cli::convert(cli).unwrap().options.filename_patterns;
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].to_string(), "%d%m%Y");
+    }
+
+    #[test]
+    fn test_filename_pattern_without_groups_errors() {
+        let result = Cli::try_parse_from(["ptime", "oldest", "--filename-pattern", r"\d{8}"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ValueValidation
+        );
+    }
+
+    #[test]
+    fn test_dir_pattern() {
+        let cli = Cli::try_parse_from([
+            "ptime",
+            "hist",
+            "--dir-pattern",
+            r"(?P<year>\d{4})/(?P<month>\d{2})",
+        ])
+        .unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().options.dir_patterns.len(), 1);
+
+        let result = Cli::try_parse_from(["ptime", "hist", "--dir-pattern", r"\d{4}"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ValueValidation
+        );
+    }
+
+    #[test]
+    fn test_fallback_is_appended_last() {
+        let cli = Cli::try_parse_from(["ptime", "oldest"]).unwrap();
+        let sources = Cli::convert(cli).unwrap().options.sources;
+        assert!(!sources.contains(&DateSource::Mtime));
+        assert!(!sources.contains(&DateSource::Btime));
+
+        let cli = Cli::try_parse_from(["ptime", "hist", "--gps", "--fallback", "btime"]).unwrap();
+        let sources = Cli::convert(cli).unwrap().options.sources;
+        assert_eq!(sources.first(), Some(&DateSource::Gps));
+        assert_eq!(sources.last(), Some(&DateSource::Btime));
+
+        let cli = Cli::try_parse_from(["ptime", "oldest", "--fallback", "atime"]);
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_jobs() {
+        let cli = Cli::try_parse_from(["ptime", "hist", "-j", "3"]).unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().options.jobs.get(), 3);
+
+        let cli = Cli::try_parse_from(["ptime", "oldest"]).unwrap();
+        let jobs = Cli::convert(cli).unwrap().options.jobs;
+        assert_eq!(jobs, crate::metadata::default_jobs());
+
+        let cli = Cli::try_parse_from(["ptime", "oldest", "--jobs", "0"]);
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_cache_flags() {
+        let cli = Cli::try_parse_from(["ptime", "hist", "--cache", "dates.tsv"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert_eq!(result.cache, Some(PathBuf::from("dates.tsv")));
+
+        let cli = Cli::try_parse_from(["ptime", "hist", "--no-cache"]).unwrap();
+        assert_eq!(Cli::convert(cli).unwrap().cache, None);
+
+        let cli = Cli::try_parse_from(["ptime", "--cache", "a.tsv", "--no-cache", "hist"]);
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_cache_command() {
+        let cli = Cli::try_parse_from(["ptime", "cache", "prune", "--cache", "dates.tsv"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Cache(CacheAction::Prune)
+        ));
+        assert_eq!(result.cache, Some(PathBuf::from("dates.tsv")));
+
+        let cli = Cli::try_parse_from(["ptime", "--no-cache", "cache", "clear"]).unwrap();
+        assert!(Cli::convert(cli).is_err());
+    }
+
+    #[test]
+    fn test_calendar_year() {
+        let cli = Cli::try_parse_from(["ptime", "calendar", "--year", "2023", "pics"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Calendar { year: Some(2023) }
+        ));
+        assert_eq!(result.directory, PathBuf::from("pics"));
+    }
+
+    #[test]
+    fn test_punchcard_with_default_dir() {
+        let cli = Cli::try_parse_from(["ptime", "punchcard"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(result.kind, CommandKind::Punchcard));
+        assert_eq!(result.directory, PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_stats_with_dir() {
+        let cli = Cli::try_parse_from(["ptime", "stats", "--since", "2020", "pics"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(result.kind, CommandKind::Stats));
+        assert_eq!(result.directory, PathBuf::from("pics"));
+    }
+
+    #[test]
+    fn test_since_until() {
+        let cli = Cli::try_parse_from(["ptime", "latest", "--until", "2019-12"]).unwrap();
+        assert_eq!(cli.global.until, Some(DateBound::parse("2019-12").unwrap()));
+        assert!(Cli::convert(cli).is_ok());
+
+        let cli = Cli::try_parse_from(["ptime", "hist", "--since", "5y"]).unwrap();
+        assert!(Cli::convert(cli).is_ok());
+
+        assert!(Cli::try_parse_from(["ptime", "hist", "--since", "last week"]).is_err());
+
+        let cli =
+            Cli::try_parse_from(["ptime", "hist", "--since", "2021", "--until", "2020"]).unwrap();
+        assert!(Cli::convert(cli).is_err());
+    }
+
+    #[test]
+    fn test_show_source_flag() {
+        let cli = Cli::try_parse_from(["ptime", "oldest", "--show-source"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Oldest { format } if format.source && !format.timestamp
+        ));
+    }
+
+    #[test]
+    fn test_hist_by_source_flag() {
+        let cli = Cli::try_parse_from(["ptime", "hist", "--by-source"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Hist {
+                width: 50,
+                by_source: true,
+                by: Granularity::Year,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_hist_by_flag() {
+        let cli = Cli::try_parse_from(["ptime", "hist", "--by", "month"]).unwrap();
+        let result = Cli::convert(cli).unwrap();
+        assert!(matches!(
+            result.kind,
+            CommandKind::Hist {
+                by: Granularity::Month,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["ptime", "hist", "--by", "decade"]).is_err());
+    }
+
     #[test]
     fn test_hist_with_default_width() {
         let cli = Cli {
+            global: GlobalArgs::default(),
             command: Command::Hist {
                 width: 50,
+                by_source: false,
+                by: Granularity::Year,
                 directory: None,
             },
         };
         let result = Cli::convert(cli).unwrap();
         match result.kind {
-            CommandKind::Hist { width } => assert_eq!(width, 50),
+            CommandKind::Hist { width, .. } => assert_eq!(width, 50),
             _ => panic!("Expected Hist command"),
         }
         assert_eq!(result.directory, PathBuf::from("."));
@@ -142,14 +703,17 @@ mod tests {
     #[test]
     fn test_hist_with_custom_width() {
         let cli = Cli {
+            global: GlobalArgs::default(),
             command: Command::Hist {
                 width: 100,
+                by_source: false,
+                by: Granularity::Year,
                 directory: None,
             },
         };
         let result = Cli::convert(cli).unwrap();
         match result.kind {
-            CommandKind::Hist { width } => assert_eq!(width, 100),
+            CommandKind::Hist { width, .. } => assert_eq!(width, 100),
             _ => panic!("Expected Hist command"),
         }
     }
@@ -157,14 +721,17 @@ mod tests {
     #[test]
     fn test_hist_width_clamped_above_200() {
         let cli = Cli {
+            global: GlobalArgs::default(),
             command: Command::Hist {
                 width: 300,
+                by_source: false,
+                by: Granularity::Year,
                 directory: None,
             },
         };
         let result = Cli::convert(cli).unwrap();
         match result.kind {
-            CommandKind::Hist { width } => assert_eq!(width, 200),
+            CommandKind::Hist { width, .. } => assert_eq!(width, 200),
             _ => panic!("Expected Hist command"),
         }
     }
@@ -181,8 +748,11 @@ mod tests {
     #[test]
     fn test_hist_with_custom_dir() {
         let cli = Cli {
+            global: GlobalArgs::default(),
             command: Command::Hist {
                 width: 50,
+                by_source: false,
+                by: Granularity::Year,
                 directory: Some(PathBuf::from("/tmp/pics")),
             },
         };
@@ -0,0 +1,678 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+const PNG_SIGNATURE: [u8; 8] = *b"\x89PNG\r\n\x1a\n";
+/// Prefix some writers leave in front of the TIFF structure
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// Upper bound for an EXIF payload, matching the JPEG APP1 segment limit
+/// with generous headroom for containers that do not have one.
+const MAX_EXIF_SIZE: u32 = 4 * 1024 * 1024;
+/// Segments up to this size are read and discarded rather than seeked over:
+/// a seek empties a `BufReader`, which then refills from disk.
+const SKIP_BY_READING: u64 = 4096;
+/// Namespace that identifies the standard XMP packet in a JPEG APP1 segment
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// TIFF magic numbers: the standard 42, then the Olympus (`RO`, `RS`) and
+/// Panasonic (`U`) RAW variants of the same structure
+const TIFF_MAGICS: [u16; 4] = [42, 0x4F52, 0x5352, 0x0055];
+/// Out-of-line TIFF values larger than this (maker notes, strip tables,
+/// embedded profiles) hold no dates and are left behind.
+const MAX_TIFF_VALUE: usize = 4096;
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const GPS_IFD_POINTER: u16 = 0x8825;
+const INTEROP_IFD_POINTER: u16 = 0xA005;
+
+/// Walks PNG chunks and returns the TIFF-structured payload of the
+/// `eXIf` chunk (PNG 1.5), or `None` when the file has no such chunk.
+pub fn read_png_exif<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature)?;
+    if signature != PNG_SIGNATURE {
+        return Err(invalid_data("not a PNG file"));
+    }
+
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        match &header[4..8] {
+            b"eXIf" => return read_payload(reader, length).map(Some),
+            b"IEND" => return Ok(None),
+            // Skip chunk data and its CRC
+            _ => skip(reader, u64::from(length) + 4)?,
+        }
+    }
+}
+
+/// Walks the chunks of a RIFF WebP file and returns the TIFF-structured
+/// payload of the `EXIF` chunk, or `None` when the file has no such chunk.
+pub fn read_webp_exif<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WEBP" {
+        return Err(invalid_data("not a WebP file"));
+    }
+    let riff_size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    // The RIFF size counts the "WEBP" fourcc that was already read
+    let mut remaining = u64::from(riff_size).saturating_sub(4);
+
+    while remaining >= 8 {
+        let mut chunk = [0u8; 8];
+        reader.read_exact(&mut chunk)?;
+        let length = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        if &chunk[0..4] == b"EXIF" {
+            return read_payload(reader, length).map(Some);
+        }
+        // Chunks are padded to an even size
+        let padded = u64::from(length) + u64::from(length & 1);
+        skip(reader, padded)?;
+        remaining = remaining.saturating_sub(8 + padded);
+    }
+
+    Ok(None)
+}
+
+/// Walks the JPEG marker segments up to the start of scan and returns the
+/// TIFF-structured payload of the EXIF APP1 segment, or `None` when there is
+/// none. Only segment headers and the EXIF segment itself are read, never
+/// the image data, which matters for large files on network storage.
+pub fn read_jpeg_exif<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    Ok(read_jpeg_app1(reader, true, false)?.exif)
+}
+
+/// Walks the JPEG marker segments up to the start of scan and returns the
+/// standard XMP packet stored in an APP1 segment, or `None` when there is
+/// none. Extended XMP split across several segments is not reassembled.
+pub fn read_jpeg_xmp<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    Ok(read_jpeg_app1(reader, false, true)?.xmp)
+}
+
+/// EXIF payload and XMP packet of a JPEG file, as far as they were asked
+/// for and found.
+#[derive(Debug, Default, PartialEq)]
+pub struct JpegApp1 {
+    pub exif: Option<Vec<u8>>,
+    pub xmp: Option<Vec<u8>>,
+}
+
+/// Collects the first EXIF and the first standard XMP APP1 segment, each
+/// only when asked for, in a single walk of the marker segments. The walk
+/// stops as soon as everything asked for is found, or at the start of scan.
+/// Other segments are skipped after reading their first bytes.
+pub fn read_jpeg_app1<R: Read + Seek>(
+    reader: &mut R,
+    exif: bool,
+    xmp: bool,
+) -> io::Result<JpegApp1> {
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi)?;
+    if soi != [0xFF, 0xD8] {
+        return Err(invalid_data("not a JPEG file"));
+    }
+
+    let mut found = JpegApp1::default();
+    while (exif && found.exif.is_none()) || (xmp && found.xmp.is_none()) {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Err(invalid_data("invalid JPEG marker"));
+        }
+        match marker[1] {
+            // Fill byte before the actual marker
+            0xFF => {
+                reader.seek(SeekFrom::Current(-1))?;
+                continue;
+            }
+            // Standalone markers without a length
+            0x01 | 0xD0..=0xD7 => continue,
+            // Start of scan or end of image: no metadata follows
+            0xDA | 0xD9 => break,
+            _ => {}
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let length = u16::from_be_bytes(length)
+            .checked_sub(2)
+            .ok_or_else(|| invalid_data("invalid JPEG segment length"))?;
+        let length = usize::from(length);
+        if marker[1] != 0xE1 {
+            skip(reader, length as u64)?;
+            continue;
+        }
+
+        // Enough of the segment to recognize either header
+        let mut prefix = vec![0u8; XMP_HEADER.len().min(length)];
+        reader.read_exact(&mut prefix)?;
+        let remaining = length - prefix.len();
+        let target = if exif && found.exif.is_none() && prefix.starts_with(EXIF_HEADER) {
+            prefix.drain(..EXIF_HEADER.len());
+            &mut found.exif
+        } else if xmp && found.xmp.is_none() && prefix == XMP_HEADER {
+            prefix.clear();
+            &mut found.xmp
+        } else {
+            skip(reader, remaining as u64)?;
+            continue;
+        };
+        let start = prefix.len();
+        prefix.resize(start + remaining, 0);
+        reader.read_exact(&mut prefix[start..])?;
+        *target = Some(prefix);
+    }
+    Ok(found)
+}
+
+/// Walks the IFDs of a TIFF or TIFF-based RAW file and returns a compact
+/// TIFF structure holding IFD0 and the EXIF and GPS IFDs it points to, or
+/// `None` when the file has no IFD. Only the IFDs and their small values
+/// are read, never the image data, and vendor magic numbers are replaced by
+/// the standard 42.
+pub fn read_tiff_exif<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let order = match &header[0..2] {
+        b"II" => ByteOrder::Little,
+        b"MM" => ByteOrder::Big,
+        _ => return Err(invalid_data("not a TIFF file")),
+    };
+    if !TIFF_MAGICS.contains(&order.u16(&header[2..4])) {
+        return Err(invalid_data("not a TIFF file"));
+    }
+    let ifd0 = order.u32(&header[4..8]);
+    if ifd0 == 0 {
+        return Ok(None);
+    }
+
+    let mut tiff = header[0..2].to_vec();
+    tiff.extend_from_slice(&order.u16_bytes(42));
+    tiff.extend_from_slice(&order.u32_bytes(8));
+    copy_ifd(reader, order, ifd0, true, &mut tiff)?;
+    Ok(Some(tiff))
+}
+
+/// Appends the IFD at `offset` to `tiff`, followed by its out-of-line
+/// values at their new offsets. Entries with unknown types or large values
+/// are dropped, and so is the link to the next IFD. With `follow`, the EXIF
+/// and GPS IFDs are appended after it; other IFD pointers are dropped.
+fn copy_ifd<R: Read + Seek>(
+    reader: &mut R,
+    order: ByteOrder,
+    offset: u32,
+    follow: bool,
+    tiff: &mut Vec<u8>,
+) -> io::Result<()> {
+    reader.seek(SeekFrom::Start(offset.into()))?;
+    let mut count = [0u8; 2];
+    reader.read_exact(&mut count)?;
+    let mut table = vec![0u8; usize::from(order.u16(&count)) * 12];
+    reader.read_exact(&mut table)?;
+
+    let mut entries = Vec::new();
+    let mut values = Vec::new();
+    let mut children = Vec::new();
+    for entry in table.chunks_exact(12) {
+        match order.u16(&entry[0..2]) {
+            EXIF_IFD_POINTER | GPS_IFD_POINTER if follow => {
+                children.push((entries.len(), order.u32(&entry[8..12])));
+            }
+            EXIF_IFD_POINTER | GPS_IFD_POINTER | INTEROP_IFD_POINTER => continue,
+            _ => {
+                let Some(length) = tiff_value_size(order.u16(&entry[2..4]))
+                    .and_then(|size| size.checked_mul(order.u32(&entry[4..8]) as usize))
+                    .filter(|&length| length <= MAX_TIFF_VALUE)
+                else {
+                    continue;
+                };
+                if length > 4 {
+                    reader.seek(SeekFrom::Start(order.u32(&entry[8..12]).into()))?;
+                    let mut value = vec![0u8; length];
+                    reader.read_exact(&mut value)?;
+                    values.push((entries.len(), value));
+                }
+            }
+        }
+        entries.push(entry);
+    }
+
+    let start = tiff.len();
+    tiff.extend_from_slice(&order.u16_bytes(entries.len() as u16));
+    for entry in &entries {
+        tiff.extend_from_slice(entry);
+    }
+    tiff.extend_from_slice(&[0; 4]);
+    // Patches the value field of entry `index` to point at the end of `tiff`
+    let point_here = |tiff: &mut Vec<u8>, index: usize| -> io::Result<()> {
+        // Values start on a word boundary
+        if tiff.len() % 2 == 1 {
+            tiff.push(0);
+        }
+        if tiff.len() > MAX_EXIF_SIZE as usize {
+            return Err(invalid_data("EXIF data too large"));
+        }
+        let at = start + 2 + index * 12 + 8;
+        let here = order.u32_bytes(tiff.len() as u32);
+        tiff[at..at + 4].copy_from_slice(&here);
+        Ok(())
+    };
+    for (index, value) in values {
+        point_here(tiff, index)?;
+        tiff.extend_from_slice(&value);
+    }
+    for (index, child) in children {
+        point_here(tiff, index)?;
+        copy_ifd(reader, order, child, false, tiff)?;
+    }
+    Ok(())
+}
+
+/// Size in bytes of one value of a TIFF field type, `None` for types the
+/// EXIF parser does not know.
+fn tiff_value_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    fn u16_bytes(self, value: u16) -> [u8; 2] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+
+    fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+fn read_payload<R: Read>(reader: &mut R, length: u32) -> io::Result<Vec<u8>> {
+    if length > MAX_EXIF_SIZE {
+        return Err(invalid_data("EXIF chunk too large"));
+    }
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload)?;
+    if payload.starts_with(EXIF_HEADER) {
+        payload.drain(..EXIF_HEADER.len());
+    }
+    Ok(payload)
+}
+
+fn skip<R: Read + Seek>(reader: &mut R, length: u64) -> io::Result<()> {
+    if length <= SKIP_BY_READING {
+        io::copy(&mut reader.by_ref().take(length), &mut io::sink())?;
+        return Ok(());
+    }
+    let offset = i64::try_from(length).map_err(|_| invalid_data("chunk too large"))?;
+    reader.seek(SeekFrom::Current(offset))?;
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TIFF: &[u8] = b"II*\0\x08\0\0\0\0\0\0\0\0\0";
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0, 0, 0, 0]); // CRC is not checked
+        chunk
+    }
+
+    fn png(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = PNG_SIGNATURE.to_vec();
+        for chunk in chunks {
+            file.extend_from_slice(chunk);
+        }
+        file
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn jpeg(segments: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![0xFF, 0xD8];
+        for segment in segments {
+            file.extend_from_slice(segment);
+        }
+        file.extend_from_slice(&[0xFF, 0xDA, 0, 2, 0xFF, 0xD9]);
+        file
+    }
+
+    fn webp_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        file.extend_from_slice(b"WEBP");
+        file.extend_from_slice(&body);
+        file
+    }
+
+    #[test]
+    fn test_png_exif_after_other_chunks() {
+        let file = png(&[
+            png_chunk(b"IHDR", &[0; 13]),
+            png_chunk(b"tEXt", b"Comment\0hi"),
+            png_chunk(b"eXIf", TIFF),
+            png_chunk(b"IEND", &[]),
+        ]);
+        let payload = read_png_exif(&mut Cursor::new(file)).unwrap();
+        assert_eq!(payload.as_deref(), Some(TIFF));
+    }
+
+    #[test]
+    fn test_png_exif_strips_exif_header() {
+        let mut data = EXIF_HEADER.to_vec();
+        data.extend_from_slice(TIFF);
+        let file = png(&[png_chunk(b"eXIf", &data), png_chunk(b"IEND", &[])]);
+        let payload = read_png_exif(&mut Cursor::new(file)).unwrap();
+        assert_eq!(payload.as_deref(), Some(TIFF));
+    }
+
+    #[test]
+    fn test_png_without_exif() {
+        let file = png(&[png_chunk(b"IHDR", &[0; 13]), png_chunk(b"IEND", &[])]);
+        assert_eq!(read_png_exif(&mut Cursor::new(file)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_png_invalid_signature() {
+        let err = read_png_exif(&mut Cursor::new(b"GIF89a..".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_png_truncated() {
+        let mut file = png(&[png_chunk(b"IHDR", &[0; 13])]);
+        file.truncate(file.len() - 6);
+        let err = read_png_exif(&mut Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_webp_exif_after_odd_sized_chunk() {
+        let file = webp(&[
+            webp_chunk(b"VP8X", &[0; 10]),
+            webp_chunk(b"ICCP", &[1, 2, 3]),
+            webp_chunk(b"EXIF", TIFF),
+        ]);
+        let payload = read_webp_exif(&mut Cursor::new(file)).unwrap();
+        assert_eq!(payload.as_deref(), Some(TIFF));
+    }
+
+    #[test]
+    fn test_webp_without_exif() {
+        let file = webp(&[webp_chunk(b"VP8L", &[0; 5])]);
+        assert_eq!(read_webp_exif(&mut Cursor::new(file)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_webp_invalid_header() {
+        let err = read_webp_exif(&mut Cursor::new(b"RIFF\0\0\0\0AVI ".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_jpeg_xmp_after_exif_segment() {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta/>");
+        let file = jpeg(&[
+            jpeg_segment(0xE0, b"JFIF\0\x01\x01"),
+            jpeg_segment(0xE1, &exif),
+            jpeg_segment(0xE1, &xmp),
+        ]);
+        let packet = read_jpeg_xmp(&mut Cursor::new(file)).unwrap();
+        assert_eq!(packet.as_deref(), Some(&b"<x:xmpmeta/>"[..]));
+    }
+
+    #[test]
+    fn test_jpeg_exif_after_xmp_segment() {
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta/>");
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let file = jpeg(&[
+            jpeg_segment(0xE1, &xmp),
+            vec![0xFF],
+            jpeg_segment(0xE1, &exif),
+        ]);
+        let payload = read_jpeg_exif(&mut Cursor::new(file)).unwrap();
+        assert_eq!(payload.as_deref(), Some(TIFF));
+    }
+
+    #[test]
+    fn test_jpeg_exif_stops_after_segment() {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let segments = [
+            jpeg_segment(0xE0, b"JFIF\0\x01\x01"),
+            jpeg_segment(0xE1, &exif),
+        ];
+        let header_len: usize = 2 + segments.iter().map(Vec::len).sum::<usize>();
+        let mut file = jpeg(&segments);
+        // Scan data after the headers is never touched
+        file.extend(std::iter::repeat_n(0xAB, 1 << 20));
+
+        let mut cursor = Cursor::new(file);
+        assert!(read_jpeg_exif(&mut cursor).unwrap().is_some());
+        assert_eq!(cursor.position() as usize, header_len);
+    }
+
+    #[test]
+    fn test_jpeg_exif_not_searched_past_start_of_scan() {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let mut file = jpeg(&[jpeg_segment(0xE0, b"JFIF\0\x01\x01")]);
+        file.extend(jpeg_segment(0xE1, &exif));
+        assert_eq!(read_jpeg_exif(&mut Cursor::new(file)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_jpeg_app1_finds_both_in_one_walk() {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta/>");
+        let segments = [
+            jpeg_segment(0xE1, &exif),
+            jpeg_segment(0xE2, &[0; 5000]),
+            jpeg_segment(0xE1, &xmp),
+        ];
+        let header_len: usize = 2 + segments.iter().map(Vec::len).sum::<usize>();
+        let mut file = jpeg(&segments);
+        file.extend(std::iter::repeat_n(0xAB, 1 << 20));
+
+        let mut cursor = Cursor::new(file);
+        let found = read_jpeg_app1(&mut cursor, true, true).unwrap();
+        assert_eq!(found.exif.as_deref(), Some(TIFF));
+        assert_eq!(found.xmp.as_deref(), Some(&b"<x:xmpmeta/>"[..]));
+        assert_eq!(cursor.position() as usize, header_len);
+
+        // Nothing asked for is not searched for
+        cursor.set_position(0);
+        let found = read_jpeg_app1(&mut cursor, true, false).unwrap();
+        assert_eq!(found.xmp, None);
+        assert_eq!(cursor.position() as usize, 2 + segments[0].len());
+    }
+
+    #[test]
+    fn test_jpeg_app1_finds_exif_after_xmp() {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(TIFF);
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta/>");
+        let file = jpeg(&[jpeg_segment(0xE1, &xmp), jpeg_segment(0xE1, &exif)]);
+
+        let found = read_jpeg_app1(&mut Cursor::new(&file), true, true).unwrap();
+        assert_eq!(found.exif.as_deref(), Some(TIFF));
+        assert_eq!(found.xmp.as_deref(), Some(&b"<x:xmpmeta/>"[..]));
+        assert_eq!(
+            read_jpeg_exif(&mut Cursor::new(&file)).unwrap().as_deref(),
+            Some(TIFF)
+        );
+    }
+
+    #[test]
+    fn test_jpeg_without_xmp() {
+        let file = jpeg(&[jpeg_segment(0xE0, b"JFIF\0\x01\x01")]);
+        assert_eq!(read_jpeg_xmp(&mut Cursor::new(file)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_jpeg_invalid_signature() {
+        let err = read_jpeg_xmp(&mut Cursor::new(b"GIF89a".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Counts the bytes actually read, wherever the reader seeks.
+    struct Counting {
+        inner: Cursor<Vec<u8>>,
+        read: usize,
+    }
+
+    impl Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    impl Seek for Counting {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn ifd_entry(tag: u16, kind: u16, count: u32, value: u32) -> Vec<u8> {
+        let mut entry = tag.to_le_bytes().to_vec();
+        entry.extend_from_slice(&kind.to_le_bytes());
+        entry.extend_from_slice(&count.to_le_bytes());
+        entry.extend_from_slice(&value.to_le_bytes());
+        entry
+    }
+
+    /// A RAW-like file: IFD0 with a make, a 10 KB maker note and the EXIF
+    /// pointer, the EXIF IFD with a capture date, 1 MB of image data, and
+    /// a thumbnail IFD at the very end.
+    fn raw_file(magic: &[u8; 4]) -> Vec<u8> {
+        let ifd1 = 1 << 20;
+        let mut file = magic.to_vec();
+        file.extend_from_slice(&8u32.to_le_bytes());
+        file.extend_from_slice(&3u16.to_le_bytes());
+        file.extend(ifd_entry(0x010F, 2, 6, 62));
+        file.extend(ifd_entry(0x927C, 7, 10_000, 2000));
+        file.extend(ifd_entry(EXIF_IFD_POINTER, 4, 1, 80));
+        file.extend_from_slice(&(ifd1 as u32).to_le_bytes());
+        file.extend_from_slice(b"NIKON\0");
+        file.resize(80, 0);
+        file.extend_from_slice(&2u16.to_le_bytes());
+        file.extend(ifd_entry(0x9003, 2, 20, 110));
+        file.extend(ifd_entry(INTEROP_IFD_POINTER, 4, 1, 500_000));
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(b"2018:05:20 07:15:00\0");
+        file.resize(ifd1, 0xAB);
+        file.extend_from_slice(&[0; 6]);
+        file
+    }
+
+    #[test]
+    fn test_tiff_exif_reads_only_the_ifds() {
+        let mut reader = Counting {
+            inner: Cursor::new(raw_file(b"II*\0")),
+            read: 0,
+        };
+        let payload = read_tiff_exif(&mut reader).unwrap().unwrap();
+        assert!(reader.read < 200, "read {} bytes", reader.read);
+
+        let exif = exif::Reader::new().read_raw(payload).unwrap();
+        let original = exif
+            .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+            .unwrap();
+        assert_eq!(original.display_value().to_string(), "2018-05-20 07:15:00");
+        assert!(exif.get_field(exif::Tag::Make, exif::In::PRIMARY).is_some());
+        assert!(exif
+            .get_field(exif::Tag::MakerNote, exif::In::PRIMARY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_tiff_exif_rewrites_vendor_magic() {
+        let payload = read_tiff_exif(&mut Cursor::new(raw_file(b"IIRO")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(&payload[0..4], b"II*\0");
+        assert!(exif::Reader::new().read_raw(payload).is_ok());
+
+        let payload = read_tiff_exif(&mut Cursor::new(b"MMOR\0\0\0\x08\0\0\0\0\0\0".to_vec()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(&payload[0..4], b"MM\0*");
+    }
+
+    #[test]
+    fn test_tiff_exif_invalid_or_empty() {
+        let err = read_tiff_exif(&mut Cursor::new(b"GIF89a..".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let empty = read_tiff_exif(&mut Cursor::new(b"II*\0\0\0\0\0".to_vec())).unwrap();
+        assert_eq!(empty, None);
+
+        let mut truncated = raw_file(b"II*\0");
+        truncated.truncate(100);
+        let err = read_tiff_exif(&mut Cursor::new(truncated)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::metadata::{parse_utc_offset, Precision};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use clap::ValueEnum;
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// `YYYY:MM:DD` at the start of a value, with any of the separators seen in
+/// the wild and spaces allowed in place of digits
+const DATE: &str =
+    r"^(?P<year>[\d ]{4})(?P<sep>[:\-/.])(?P<month>[\d ]{2})(?P<sep2>[:\-/.])(?P<day>[\d ]{2})";
+/// `hh:mm[:ss][.fraction][zone]` after the date
+const TIME: &str = r"^(?P<hour>[\d ]{2}):(?P<minute>[\d ]{2})(?::(?P<second>[\d ]{2}))?(?P<fraction>[.,]\d+)?(?:\s*(?P<zone>Z|[+-]\d{2}(?::?\d{2})?))?(?P<trailing>\s*)$";
+
+/// How forgiving the EXIF date parser is, selected with `--date-parsing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ParseMode {
+    /// Only the EXIF layout `YYYY:MM:DD[ hh:mm:ss]`
+    Strict,
+    /// Repair common deviations and report them
+    #[default]
+    Lenient,
+}
+
+/// A repair applied to a malformed date value so that it parses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Spaces around the value, or more than one between date and time
+    Whitespace,
+    /// `-`, `/` or `.` between the date fields
+    Separator,
+    /// `T` between date and time, as in ISO 8601
+    Delimiter,
+    /// Trailing `Z` or UTC offset
+    Zone,
+    /// Fractional seconds after the time
+    Fraction,
+    /// Spaces in place of digits, read as an unknown field
+    Blanks,
+    /// Time without seconds
+    ShortTime,
+    /// Unreadable time of day, dropped to keep the date
+    BadTime,
+}
+
+impl Normalization {
+    const ALL: [Normalization; 8] = [
+        Normalization::Whitespace,
+        Normalization::Separator,
+        Normalization::Delimiter,
+        Normalization::Zone,
+        Normalization::Fraction,
+        Normalization::Blanks,
+        Normalization::ShortTime,
+        Normalization::BadTime,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Normalization::Whitespace => "whitespace",
+            Normalization::Separator => "separator",
+            Normalization::Delimiter => "delimiter",
+            Normalization::Zone => "zone",
+            Normalization::Fraction => "fraction",
+            Normalization::Blanks => "blanks",
+            Normalization::ShortTime => "short-time",
+            Normalization::BadTime => "bad-time",
+        }
+    }
+}
+
+/// The set of normalizations applied to one value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Normalizations(u8);
+
+impl Normalizations {
+    pub fn insert(&mut self, normalization: Normalization) {
+        self.0 |= normalization.bit();
+    }
+
+    pub fn contains(&self, normalization: Normalization) -> bool {
+        self.0 & normalization.bit() != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Raw bit set, as stored in the cache
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Normalizations(bits)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Normalization> + '_ {
+        Normalization::ALL
+            .into_iter()
+            .filter(|normalization| self.contains(*normalization))
+    }
+}
+
+/// Names joined with `+`, e.g. `separator+delimiter`
+impl fmt::Display for Normalizations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, normalization) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            f.write_str(normalization.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// A date value read from an EXIF text field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParsedDateTime {
+    pub datetime: NaiveDateTime,
+    /// Zone written into the value itself, which EXIF does not allow
+    pub offset: Option<FixedOffset>,
+    pub precision: Precision,
+    /// Repairs needed to read the value; always empty in strict mode
+    pub normalizations: Normalizations,
+}
+
+/// Parses an EXIF date and time, `YYYY:MM:DD hh:mm:ss` or the date alone.
+///
+/// Unknown fields may be zero-filled: `2005:00:00` is the year 2005 and
+/// `2005:07:00` July 2005, while a zero or blank year (including the
+/// all-blank `    :  :     :  :  ` placeholder) means no date at all. In
+/// lenient mode, values written by non-conforming software are repaired and
+/// the repairs reported; strict mode rejects any value needing one.
+pub fn parse_exif_datetime(value: &str, mode: ParseMode) -> Option<ParsedDateTime> {
+    let parsed = parse_lenient(value)?;
+    match mode {
+        ParseMode::Strict if !parsed.normalizations.is_empty() => None,
+        _ => Some(parsed),
+    }
+}
+
+fn parse_lenient(value: &str) -> Option<ParsedDateTime> {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let [date_regex, time_regex] = PATTERNS
+        .get_or_init(|| [DATE, TIME].map(|p| Regex::new(p).expect("built-in pattern is valid")));
+
+    let mut normalizations = Normalizations::default();
+    // Trailing spaces may be blank digits, so only the start is trimmed here
+    let untrimmed = value.trim_matches('\0');
+    let value = untrimmed.trim_start();
+    if value.len() != untrimmed.len() {
+        normalizations.insert(Normalization::Whitespace);
+    }
+
+    let captures = date_regex.captures(value)?;
+    if captures["sep"] != captures["sep2"] {
+        return None;
+    }
+    if &captures["sep"] != ":" {
+        normalizations.insert(Normalization::Separator);
+    }
+    let mut field = |name: &str| parse_field(&captures[name], &mut normalizations);
+    let (year, month, day) = (field("year")?, field("month")?, field("day")?);
+
+    let year = i32::try_from(year.filter(|y| *y > 0)?).ok()?;
+    let (date, precision) = match (month.unwrap_or(0), day.unwrap_or(0)) {
+        (0, _) => (NaiveDate::from_ymd_opt(year, 1, 1)?, Precision::Year),
+        (month, 0) => (NaiveDate::from_ymd_opt(year, month, 1)?, Precision::Month),
+        (month, day) => (NaiveDate::from_ymd_opt(year, month, day)?, Precision::Day),
+    };
+
+    let rest = &value[captures.get(0)?.end()..];
+    let mut parsed = ParsedDateTime {
+        datetime: date.and_time(NaiveTime::MIN),
+        offset: None,
+        precision,
+        normalizations,
+    };
+    if rest.trim().is_empty() {
+        if !rest.is_empty() {
+            parsed.normalizations.insert(Normalization::Whitespace);
+        }
+        return Some(parsed);
+    }
+
+    let time = match rest.strip_prefix('T') {
+        Some(time) => {
+            parsed.normalizations.insert(Normalization::Delimiter);
+            time
+        }
+        // Digits or other text running on from the date are no time
+        None => rest.strip_prefix(' ')?,
+    };
+    if time.chars().all(|c| c == ' ' || c == ':') {
+        // Blank placeholder for an unknown time of day
+        parsed.normalizations.insert(Normalization::Blanks);
+        return Some(parsed);
+    }
+
+    let mut time_normalizations = parsed.normalizations;
+    let mut clock = parse_time(time_regex, time, &mut time_normalizations);
+    if clock.is_none() && time.starts_with(' ') {
+        time_normalizations = parsed.normalizations;
+        time_normalizations.insert(Normalization::Whitespace);
+        clock = parse_time(time_regex, time.trim_start(), &mut time_normalizations);
+    }
+    match clock {
+        // A partial date keeps its precision, whatever the time says
+        Some(Some(clock)) if precision == Precision::Day => {
+            parsed.datetime = date.and_time(clock.time);
+            parsed.offset = clock.offset;
+            parsed.precision = Precision::Time;
+            parsed.normalizations = time_normalizations;
+        }
+        Some(_) => parsed.normalizations = time_normalizations,
+        None => parsed.normalizations.insert(Normalization::BadTime),
+    }
+    Some(parsed)
+}
+
+/// Time of day read from the time part of a value.
+struct Clock {
+    time: NaiveTime,
+    /// Trailing `Z` or offset written after the time
+    offset: Option<FixedOffset>,
+}
+
+/// Reads a time of day and zone: `None` when unreadable, `Some(None)` when
+/// the hour or minute is blank.
+fn parse_time(
+    regex: &Regex,
+    time: &str,
+    normalizations: &mut Normalizations,
+) -> Option<Option<Clock>> {
+    let captures = regex.captures(time)?;
+    let hour = parse_field(&captures["hour"], normalizations)?;
+    let minute = parse_field(&captures["minute"], normalizations)?;
+    let second = match captures.name("second") {
+        Some(second) => parse_field(second.as_str(), normalizations)?,
+        None => {
+            normalizations.insert(Normalization::ShortTime);
+            Some(0)
+        }
+    };
+    let nanos = match captures.name("fraction") {
+        Some(fraction) => {
+            normalizations.insert(Normalization::Fraction);
+            let digits: String = fraction.as_str()[1..]
+                .chars()
+                .chain(std::iter::repeat('0'))
+                .take(9)
+                .collect();
+            digits.parse().ok()?
+        }
+        None => 0,
+    };
+    if !captures["trailing"].is_empty() {
+        normalizations.insert(Normalization::Whitespace);
+    }
+    let offset = match captures.name("zone") {
+        Some(zone) => {
+            normalizations.insert(Normalization::Zone);
+            Some(parse_utc_offset(zone.as_str())?)
+        }
+        None => None,
+    };
+
+    let (Some(hour), Some(minute)) = (hour, minute) else {
+        return Some(None);
+    };
+    let time = NaiveTime::from_hms_nano_opt(hour, minute, second.unwrap_or(0), nanos)?;
+    Some(Some(Clock { time, offset }))
+}
+
+/// Reads a numeric field: `Some(None)` when it is blank, `None` when it is
+/// not a number. Spaces standing in for digits are recorded.
+fn parse_field(field: &str, normalizations: &mut Normalizations) -> Option<Option<u32>> {
+    let digits = field.trim_matches(' ');
+    if digits.len() != field.len() {
+        normalizations.insert(Normalization::Blanks);
+    }
+    if digits.is_empty() {
+        return Some(None);
+    }
+    digits.parse().ok().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Corpus of values found in real files: input, strict result, lenient
+    /// result and the normalizations applied in lenient mode
+    const CORPUS: &str = include_str!("../tests/fixtures/exif_datetimes.tsv");
+
+    /// Renders a parse result in the notation used by the corpus
+    fn render(parsed: Option<ParsedDateTime>) -> String {
+        let Some(parsed) = parsed else {
+            return "-".to_string();
+        };
+        let mut text = match parsed.precision {
+            Precision::Year => parsed.datetime.format("%Y").to_string(),
+            Precision::Month => parsed.datetime.format("%Y-%m").to_string(),
+            Precision::Day => parsed.datetime.format("%Y-%m-%d").to_string(),
+            Precision::Time => parsed.datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+        };
+        if let Some(offset) = parsed.offset {
+            text.push_str(&offset.to_string());
+        }
+        text
+    }
+
+    #[test]
+    fn test_corpus() {
+        let mut cases = 0;
+        for line in CORPUS.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [input, strict, lenient, normalizations] = fields[..] else {
+                panic!("malformed corpus line: {line:?}");
+            };
+            let input = input
+                .strip_prefix('"')
+                .and_then(|i| i.strip_suffix('"'))
+                .expect("inputs are quoted");
+
+            let parsed = parse_exif_datetime(input, ParseMode::Lenient);
+            assert_eq!(render(parsed), lenient, "lenient {input:?}");
+            let applied = parsed.map(|p| p.normalizations.to_string());
+            let expected = Some(normalizations)
+                .filter(|n| *n != "-")
+                .unwrap_or_default();
+            assert_eq!(
+                applied.as_deref().unwrap_or_default(),
+                expected,
+                "normalizations {input:?}"
+            );
+            assert_eq!(
+                render(parse_exif_datetime(input, ParseMode::Strict)),
+                strict,
+                "strict {input:?}"
+            );
+            cases += 1;
+        }
+        assert!(cases >= 30, "corpus has only {cases} cases");
+    }
+
+    #[test]
+    fn test_normalizations_display() {
+        let mut normalizations = Normalizations::default();
+        assert_eq!(normalizations.to_string(), "");
+        normalizations.insert(Normalization::Zone);
+        normalizations.insert(Normalization::Separator);
+        assert_eq!(normalizations.to_string(), "separator+zone");
+        assert!(normalizations.contains(Normalization::Zone));
+        assert!(!normalizations.contains(Normalization::Blanks));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::metadata::Precision;
+use chrono::NaiveDate;
+use regex::{Captures, Regex};
+use std::fmt;
+use std::path::{Component, Path};
+use std::sync::OnceLock;
+
+/// A full date anywhere in a directory name: `2019-07-15 Beach`, `20190715`
+const FULL_DATE: &str =
+    r"(?:^|\D)(?P<year>(?:19|20)\d{2})[-_. ]?(?P<month>\d{2})[-_. ]?(?P<day>\d{2})(?:\D|$)";
+/// A year and month: `2019-07 Vacation`, `Trip 2019_07`
+const YEAR_MONTH: &str = r"(?:^|\D)(?P<year>(?:19|20)\d{2})[-_. ](?P<month>\d{2})(?:\D|$)";
+/// A year on its own: `2019`, `Summer 2019`
+const YEAR: &str = r"(?:^|\D)(?P<year>(?:19|20)\d{2})(?:\D|$)";
+/// A leading month or day number below a dated directory: `07 - Vacation`
+const NUMBER: &str = r"^(?P<number>\d{1,2})(?:\D|$)";
+/// Marks a year as an estimate: `ca. 1987`, `circa 1987`, `c1987`, `~1987`,
+/// `1987?`, or a decade such as `1980s`
+const CIRCA: &str = concat!(
+    r"(?i)(?:^|[^a-z])(?:c|ca|circa)\.?\s*(?:19|20)\d{2}",
+    r"|~\s*(?:19|20)\d{2}",
+    r"|(?:19|20)\d{2}s?\s*\?",
+    r"|(?:^|\D)(?:19|20)\d0s(?:[^a-z]|$)",
+);
+
+/// A user-supplied regex matched against the relative directory path,
+/// with `/` as separator, e.g. `(?P<year>\d{4})/(?P<month>\d{2})`.
+#[derive(Debug, Clone)]
+pub struct DirPattern(Regex);
+
+impl DirPattern {
+    /// Parses a `--dir-pattern` value, which needs a `year` group; `month`
+    /// and `day` groups are optional.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let regex = Regex::new(value).map_err(|e| format!("Invalid directory pattern: {e}"))?;
+        if !regex.capture_names().flatten().any(|name| name == "year") {
+            return Err("Directory pattern must have a named group (?P<year>...)".to_string());
+        }
+        Ok(DirPattern(regex))
+    }
+}
+
+impl PartialEq for DirPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl fmt::Display for DirPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+/// A date read from directory names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirDate {
+    /// First day of the period the directories name
+    pub date: NaiveDate,
+    pub precision: Precision,
+    /// The name marks the year as an estimate, e.g. `ca. 1987`
+    pub approximate: bool,
+}
+
+/// Known parts of a date, filled in from the root towards the file.
+#[derive(Debug, Clone, Copy)]
+struct Parts {
+    year: i32,
+    month: Option<u32>,
+    day: Option<u32>,
+    approximate: bool,
+}
+
+impl Parts {
+    /// The first day of the period and how much of it is known. Invalid
+    /// combinations such as a 31st of June yield nothing.
+    fn resolve(self) -> Option<DirDate> {
+        let (date, precision) = match (self.month, self.day) {
+            (Some(month), Some(day)) => (
+                NaiveDate::from_ymd_opt(self.year, month, day)?,
+                Precision::Day,
+            ),
+            (Some(month), None) => (
+                NaiveDate::from_ymd_opt(self.year, month, 1)?,
+                Precision::Month,
+            ),
+            (None, _) => (NaiveDate::from_ymd_opt(self.year, 1, 1)?, Precision::Year),
+        };
+        Some(DirDate {
+            date,
+            precision,
+            approximate: self.approximate,
+        })
+    }
+}
+
+/// Infers a date from the directories of `rel_path`, trying the user
+/// patterns on the whole directory path before the built-in ones, which
+/// combine names level by level: `2019/07 - Vacation/` reads as July 2019.
+pub fn date_from_dirs(rel_path: &Path, patterns: &[DirPattern]) -> Option<DirDate> {
+    let dirs: Vec<&str> = rel_path
+        .parent()?
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+    if dirs.is_empty() {
+        return None;
+    }
+
+    let joined = dirs.join("/");
+    patterns
+        .iter()
+        .find_map(|pattern| {
+            pattern
+                .0
+                .captures_iter(&joined)
+                .find_map(|captures| parts_from_captures(&captures)?.resolve())
+        })
+        .or_else(|| builtin_date(&dirs))
+}
+
+fn builtin_date(dirs: &[&str]) -> Option<DirDate> {
+    static PATTERNS: OnceLock<[Regex; 5]> = OnceLock::new();
+    let [full_date, year_month, year, number, circa] = PATTERNS.get_or_init(|| {
+        [FULL_DATE, YEAR_MONTH, YEAR, NUMBER, CIRCA]
+            .map(|pattern| Regex::new(pattern).expect("built-in pattern is valid"))
+    });
+
+    let mut parts: Option<Parts> = None;
+    for dir in dirs {
+        let dated = [full_date, year_month, year].into_iter().find_map(|regex| {
+            regex
+                .captures_iter(dir)
+                .filter_map(|captures| parts_from_captures(&captures))
+                .find(|found| found.resolve().is_some())
+        });
+        if let Some(found) = dated {
+            parts = Some(Parts {
+                approximate: circa.is_match(dir),
+                ..found
+            });
+            continue;
+        }
+
+        // A bare number refines the enclosing year or month
+        let (Some(current), Some(captures)) = (parts.as_mut(), number.captures(dir)) else {
+            continue;
+        };
+        let Ok(value) = captures["number"].parse::<u32>() else {
+            continue;
+        };
+        match (current.month, current.day) {
+            (None, _) if (1..=12).contains(&value) => current.month = Some(value),
+            (Some(_), None) => {
+                let refined = Parts {
+                    day: Some(value),
+                    ..*current
+                };
+                if refined.resolve().is_some() {
+                    *current = refined;
+                }
+            }
+            _ => {}
+        }
+    }
+    parts?.resolve()
+}
+
+fn parts_from_captures(captures: &Captures) -> Option<Parts> {
+    let number = |group: &str| captures.name(group).and_then(|m| m.as_str().parse().ok());
+    Some(Parts {
+        year: captures.name("year")?.as_str().parse().ok()?,
+        month: number("month"),
+        day: number("day"),
+        approximate: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Date and precision inferred for `path`, without user patterns
+    fn dated(path: &str) -> Option<(NaiveDate, Precision)> {
+        date_from_dirs(Path::new(path), &[]).map(|found| (found.date, found.precision))
+    }
+
+    #[test]
+    fn test_builtin_layouts() {
+        let cases = [
+            (
+                "2019/2019-07-15 Beach/IMG.jpg",
+                date(2019, 7, 15),
+                Precision::Day,
+            ),
+            (
+                "2019/07 - Vacation/IMG.jpg",
+                date(2019, 7, 1),
+                Precision::Month,
+            ),
+            ("2019/07/15/IMG.jpg", date(2019, 7, 15), Precision::Day),
+            (
+                "Archive/2005_08 Wedding/IMG.jpg",
+                date(2005, 8, 1),
+                Precision::Month,
+            ),
+            (
+                "Scans/Summer 1987/IMG.jpg",
+                date(1987, 1, 1),
+                Precision::Year,
+            ),
+            (
+                "1990s/1994/roll 3/IMG.jpg",
+                date(1994, 1, 1),
+                Precision::Year,
+            ),
+            ("20010911/IMG.jpg", date(2001, 9, 11), Precision::Day),
+        ];
+        for (path, expected, precision) in cases {
+            assert_eq!(dated(path), Some((expected, precision)), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_deeper_directories_override() {
+        assert_eq!(
+            dated("2019/2020-01 New Year/IMG.jpg"),
+            Some((date(2020, 1, 1), Precision::Month))
+        );
+    }
+
+    #[test]
+    fn test_no_date_in_directories() {
+        for path in [
+            "IMG_2019.jpg",
+            "Holidays/Beach/IMG.jpg",
+            "07/15/IMG.jpg",
+            "Camera 3000/IMG.jpg",
+        ] {
+            assert_eq!(dated(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_month_keeps_year() {
+        assert_eq!(
+            dated("2019/13 - Misc/IMG.jpg"),
+            Some((date(2019, 1, 1), Precision::Year))
+        );
+    }
+
+    #[test]
+    fn test_invalid_day_keeps_month() {
+        assert_eq!(
+            dated("2019/06/31/IMG.jpg"),
+            Some((date(2019, 6, 1), Precision::Month))
+        );
+    }
+
+    #[test]
+    fn test_user_pattern_spans_levels() {
+        let pattern = DirPattern::parse(r"Y(?P<year>\d{4})/M(?P<month>\d{2})").unwrap();
+        let found = date_from_dirs(Path::new("Y1999/M12/IMG.jpg"), &[pattern]).unwrap();
+        assert_eq!(
+            (found.date, found.precision),
+            (date(1999, 12, 1), Precision::Month)
+        );
+    }
+
+    #[test]
+    fn test_circa_markers_make_dates_approximate() {
+        let cases = [
+            ("ca. 1987/IMG.jpg", date(1987, 1, 1)),
+            ("Scans/circa 1987 Grandma/IMG.jpg", date(1987, 1, 1)),
+            ("c1987/IMG.jpg", date(1987, 1, 1)),
+            ("~1987/IMG.jpg", date(1987, 1, 1)),
+            ("1987?/IMG.jpg", date(1987, 1, 1)),
+            ("1980s/IMG.jpg", date(1980, 1, 1)),
+            ("Ca. 1987/07/IMG.jpg", date(1987, 7, 1)),
+        ];
+        for (path, expected) in cases {
+            let found = date_from_dirs(Path::new(path), &[]).unwrap();
+            assert_eq!(found.date, expected, "{path}");
+            assert!(found.approximate, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_exact_names_are_not_approximate() {
+        for path in [
+            "1987/IMG.jpg",
+            "Disc 1987/IMG.jpg",
+            "1990s/1994/IMG.jpg",
+            "1985s/IMG.jpg",
+        ] {
+            let found = date_from_dirs(Path::new(path), &[]).unwrap();
+            assert!(!found.approximate, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_parse_requires_year_group() {
+        assert!(DirPattern::parse(r"(?P<month>\d{2})").is_err());
+        assert!(DirPattern::parse(r"(?P<year>\d{4}").is_err());
+    }
+}
@@ -23,6 +23,9 @@ pub enum PtimeError {
 
     #[error("EXIF error: {0}")]
     Exif(String),
+
+    #[error("Video metadata error: {0}")]
+    Video(String),
 }
 
 impl PtimeError {
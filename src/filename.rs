@@ -0,0 +1,223 @@
+use crate::metadata::Precision;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::{Captures, Regex};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Naming schemes recognized without configuration, tried in order:
+///
+/// - Pixel: `PXL_20230102_101112345`
+/// - date and time: `IMG_20190715_101112`, `Screenshot_20190401-095236`,
+///   `Screenshot 2023-01-02 at 10.11.12`
+/// - date only: `IMG-20190715-WA0001` (WhatsApp), `scan_2004-03-09`
+const BUILTIN_PATTERNS: [&str; 3] = [
+    r"PXL_(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})_(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})(?P<millis>\d{3})?",
+    r"(?:^|\D)(?P<year>(?:19|20)\d{2})[-_.]?(?P<month>\d{2})[-_.]?(?P<day>\d{2})(?:[-_ T]|\sat\s)(?P<hour>\d{2})[-_.:h]?(?P<minute>\d{2})[-_.:m]?(?P<second>\d{2})",
+    r"(?:^|\D)(?P<year>(?:19|20)\d{2})[-_.]?(?P<month>\d{2})[-_.]?(?P<day>\d{2})(?:\D|$)",
+];
+
+/// A user-supplied way to read a date from a file name.
+#[derive(Debug, Clone)]
+pub enum FilenamePattern {
+    /// Regex with named groups `year`, `month`, `day` and optionally
+    /// `hour`, `minute`, `second`
+    Regex(Regex),
+    /// strftime template such as `%Y%m%d_%H%M%S`, matched anywhere in the name
+    Template(String),
+}
+
+impl FilenamePattern {
+    /// Parses a `--filename-pattern` value: values containing `%` are strftime
+    /// templates, anything else is a regex.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.contains('%') {
+            return Ok(FilenamePattern::Template(value.to_string()));
+        }
+        let regex = Regex::new(value).map_err(|e| format!("Invalid filename pattern: {e}"))?;
+        let names: Vec<_> = regex.capture_names().flatten().collect();
+        for group in ["year", "month", "day"] {
+            if !names.contains(&group) {
+                return Err(format!(
+                    "Filename pattern must have a named group (?P<{group}>...)"
+                ));
+            }
+        }
+        Ok(FilenamePattern::Regex(regex))
+    }
+
+    fn find(&self, name: &str) -> Option<(NaiveDateTime, Precision)> {
+        match self {
+            FilenamePattern::Regex(regex) => regex.captures_iter(name).find_map(from_captures),
+            FilenamePattern::Template(template) => find_template(name, template),
+        }
+    }
+}
+
+impl PartialEq for FilenamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl fmt::Display for FilenamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilenamePattern::Regex(regex) => f.write_str(regex.as_str()),
+            FilenamePattern::Template(template) => f.write_str(template),
+        }
+    }
+}
+
+/// Reads a capture date from a file name, trying the user patterns before
+/// the built-in ones. Names carry no time zone, so the result is local time.
+/// A name without a time of day has day precision and is stored at midnight.
+pub fn date_from_filename(
+    name: &str,
+    patterns: &[FilenamePattern],
+) -> Option<(NaiveDateTime, Precision)> {
+    patterns
+        .iter()
+        .find_map(|pattern| pattern.find(name))
+        .or_else(|| {
+            builtin_patterns()
+                .iter()
+                .find_map(|regex| regex.captures_iter(name).find_map(from_captures))
+        })
+}
+
+fn builtin_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        BUILTIN_PATTERNS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("built-in pattern is valid"))
+            .collect()
+    })
+}
+
+fn from_captures(captures: Captures) -> Option<(NaiveDateTime, Precision)> {
+    let number = |group: &str| -> Option<u32> {
+        match captures.name(group) {
+            Some(m) => m.as_str().parse().ok(),
+            None => Some(0),
+        }
+    };
+    let year = captures.name("year")?.as_str().parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, number("month")?, number("day")?)?;
+    let time = NaiveTime::from_hms_milli_opt(
+        number("hour")?,
+        number("minute")?,
+        number("second")?,
+        number("millis")?,
+    )?;
+    let precision = if captures.name("hour").is_some() {
+        Precision::Time
+    } else {
+        Precision::Day
+    };
+    Some((date.and_time(time), precision))
+}
+
+/// Tries the template at every position of `name`; templates without a time
+/// of day yield a date of day precision.
+fn find_template(name: &str, template: &str) -> Option<(NaiveDateTime, Precision)> {
+    name.char_indices().find_map(|(start, _)| {
+        let rest = &name[start..];
+        NaiveDateTime::parse_and_remainder(rest, template)
+            .map(|(datetime, _)| (datetime, Precision::Time))
+            .or_else(|_| {
+                NaiveDate::parse_and_remainder(rest, template)
+                    .map(|(date, _)| (date.and_time(NaiveTime::MIN), Precision::Day))
+            })
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> (NaiveDateTime, Precision) {
+        let datetime = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap();
+        (datetime, Precision::Time)
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> (NaiveDateTime, Precision) {
+        let (datetime, _) = datetime(y, m, d, 0, 0, 0);
+        (datetime, Precision::Day)
+    }
+
+    #[test]
+    fn test_builtin_patterns() {
+        let cases = [
+            ("IMG-20190715-WA0001.jpg", date(2019, 7, 15)),
+            ("IMG_20190715_101112.jpg", datetime(2019, 7, 15, 10, 11, 12)),
+            ("VID_20200229_235959.mp4", datetime(2020, 2, 29, 23, 59, 59)),
+            (
+                "Screenshot_20190401-095236_Maps.png",
+                datetime(2019, 4, 1, 9, 52, 36),
+            ),
+            (
+                "Screenshot 2023-01-02 at 10.11.12.png",
+                datetime(2023, 1, 2, 10, 11, 12),
+            ),
+            ("scan_2004-03-09.tif", date(2004, 3, 9)),
+            // A time of day in the name is kept even when it is midnight
+            ("IMG_20190715_000000.jpg", datetime(2019, 7, 15, 0, 0, 0)),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(date_from_filename(name, &[]), Some(expected), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_pixel_milliseconds() {
+        let (date, precision) = date_from_filename("PXL_20230102_101112345.jpg", &[]).unwrap();
+        assert_eq!(
+            date,
+            datetime(2023, 1, 2, 10, 11, 12).0 + chrono::TimeDelta::milliseconds(345)
+        );
+        assert_eq!(precision, Precision::Time);
+    }
+
+    #[test]
+    fn test_no_date_in_name() {
+        for name in [
+            "DSC_0001.JPG",
+            "IMG_1234.jpg",
+            "holiday.jpg",
+            "IMG_20191315.jpg",
+        ] {
+            assert_eq!(date_from_filename(name, &[]), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_user_regex_wins_over_builtins() {
+        let pattern =
+            FilenamePattern::parse(r"(?P<day>\d{2})\.(?P<month>\d{2})\.(?P<year>\d{4})").unwrap();
+        let found = date_from_filename("Urlaub 15.07.2019 IMG_20200101_000000.jpg", &[pattern]);
+        assert_eq!(found, Some(date(2019, 7, 15)));
+    }
+
+    #[test]
+    fn test_user_template() {
+        let pattern = FilenamePattern::parse("%d%m%y-%H%M").unwrap();
+        assert!(matches!(pattern, FilenamePattern::Template(_)));
+        let found = date_from_filename("cam1_150719-1011.jpg", &[pattern]);
+        assert_eq!(found, Some(datetime(2019, 7, 15, 10, 11, 0)));
+
+        let pattern = FilenamePattern::parse("%Y_%m_%d").unwrap();
+        let found = date_from_filename("roll_1987_06_30.jpg", &[pattern]);
+        assert_eq!(found, Some(date(1987, 6, 30)));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_patterns() {
+        assert!(FilenamePattern::parse(r"(?P<year>\d{4").is_err());
+        assert!(FilenamePattern::parse(r"(?P<year>\d{4})(?P<month>\d{2})").is_err());
+    }
+}
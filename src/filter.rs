@@ -0,0 +1,343 @@
+use crate::metadata::PhotoMeta;
+use chrono::{Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// A `--since` or `--until` value, resolved against the clock only once
+/// the target time zone is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateBound {
+    /// A calendar period such as `2019`, `2019-07`, `2019-07-15`,
+    /// `2019-07-15T10:11` or an instant, stored as its first instant
+    Period {
+        start: NaiveDateTime,
+        unit: PeriodUnit,
+    },
+    /// A span of time before now, such as `30d`
+    Ago { amount: u32, unit: AgoUnit },
+}
+
+/// Length of a calendar period: the last field written in the bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodUnit {
+    Year,
+    Month,
+    Day,
+    Minute,
+    Second,
+}
+
+/// Units of relative bounds: `h`, `d`, `w`, `m` (months) and `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgoUnit {
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl DateBound {
+    /// Parses `YYYY`, `YYYY-MM`, `YYYY-MM-DD`, `YYYY-MM-DDThh:mm[:ss]` or a
+    /// relative value such as `12h`, `30d`, `6w`, `3m` or `5y`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid date: {value} (expected YYYY, YYYY-MM, YYYY-MM-DD or an age such as 30d)"
+            )
+        };
+        let value = value.trim();
+        if let Some(bound) = parse_ago(value) {
+            return bound.ok_or_else(invalid);
+        }
+
+        let (date, time) = match value.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (value, None),
+        };
+        let mut fields = date.split('-');
+        let year = parse_number(fields.next(), 4).ok_or_else(invalid)?;
+        let month = fields.next().map(|m| parse_number(Some(m), 2));
+        let day = fields.next().map(|d| parse_number(Some(d), 2));
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+
+        let (start, unit) = match (month, day) {
+            (None, _) => (NaiveDate::from_ymd_opt(year as i32, 1, 1), PeriodUnit::Year),
+            (Some(Some(month)), None) => (
+                NaiveDate::from_ymd_opt(year as i32, month, 1),
+                PeriodUnit::Month,
+            ),
+            (Some(Some(month)), Some(Some(day))) => (
+                NaiveDate::from_ymd_opt(year as i32, month, day),
+                PeriodUnit::Day,
+            ),
+            _ => (None, PeriodUnit::Day),
+        };
+        let start = start.ok_or_else(invalid)?;
+        let (start, unit) = match time {
+            None => (start.and_time(NaiveTime::MIN), unit),
+            Some(_) if unit != PeriodUnit::Day => return Err(invalid()),
+            Some(time) => {
+                let (time, unit) = NaiveTime::parse_from_str(time, "%H:%M:%S")
+                    .map(|time| (time, PeriodUnit::Second))
+                    .or_else(|_| {
+                        NaiveTime::parse_from_str(time, "%H:%M")
+                            .map(|time| (time, PeriodUnit::Minute))
+                    })
+                    .map_err(|_| invalid())?;
+                (start.and_time(time), unit)
+            }
+        };
+        Ok(DateBound::Period { start, unit })
+    }
+
+    /// First instant covered by the bound.
+    fn start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match *self {
+            DateBound::Period { start, .. } => Some(start),
+            DateBound::Ago { amount, unit } => match unit {
+                AgoUnit::Hours => now.checked_sub_signed(Duration::hours(amount.into())),
+                AgoUnit::Days => now.checked_sub_signed(Duration::days(amount.into())),
+                AgoUnit::Weeks => now.checked_sub_signed(Duration::weeks(amount.into())),
+                AgoUnit::Months => now.checked_sub_months(Months::new(amount)),
+                AgoUnit::Years => now.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+            },
+        }
+    }
+
+    /// First instant after the bound: the end of the period, so that
+    /// `--until 2019` includes all of 2019 and `--until 2019-07-15T10:11`
+    /// the whole minute, or the instant itself for a relative bound.
+    fn end(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match *self {
+            DateBound::Period { start, unit } => match unit {
+                PeriodUnit::Year => start.checked_add_months(Months::new(12)),
+                PeriodUnit::Month => start.checked_add_months(Months::new(1)),
+                PeriodUnit::Day => start.checked_add_signed(Duration::days(1)),
+                PeriodUnit::Minute => start.checked_add_signed(Duration::minutes(1)),
+                PeriodUnit::Second => start.checked_add_signed(Duration::seconds(1)),
+            },
+            DateBound::Ago { .. } => self.start(now),
+        }
+    }
+}
+
+/// `None` when `value` is not relative, `Some(None)` when it is but out of
+/// range.
+fn parse_ago(value: &str) -> Option<Option<DateBound>> {
+    let unit = match value.chars().last()? {
+        'h' => AgoUnit::Hours,
+        'd' => AgoUnit::Days,
+        'w' => AgoUnit::Weeks,
+        'm' => AgoUnit::Months,
+        'y' => AgoUnit::Years,
+        _ => return None,
+    };
+    let digits = &value[..value.len() - 1];
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(
+        digits
+            .parse()
+            .ok()
+            .map(|amount| DateBound::Ago { amount, unit }),
+    )
+}
+
+/// Parses a field of exactly `width` digits.
+fn parse_number(field: Option<&str>, width: usize) -> Option<u32> {
+    let field = field?;
+    if field.len() != width || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+/// Window of capture times kept by `--since` and `--until`: from the first
+/// instant of the `since` bound up to, but excluding, the first instant
+/// after the `until` bound. Photos with a partial date are placed at the
+/// start of their period, as everywhere else, so a photo dated `1987` is
+/// inside `--until 1987-03` but outside `--since 1987-03`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateRange {
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+}
+
+impl DateRange {
+    /// Resolves the bounds against `now`, the current time in the zone
+    /// photos are compared in.
+    pub fn new(
+        since: Option<DateBound>,
+        until: Option<DateBound>,
+        now: NaiveDateTime,
+    ) -> Result<Self, String> {
+        let out_of_range = || "Date bound out of range".to_string();
+        let range = DateRange {
+            since: since
+                .map(|bound| bound.start(now).ok_or_else(out_of_range))
+                .transpose()?,
+            until: until
+                .map(|bound| bound.end(now).ok_or_else(out_of_range))
+                .transpose()?,
+        };
+        if let (Some(since), Some(until)) = (range.since, range.until) {
+            if since >= until {
+                return Err("--since must be before --until".to_string());
+            }
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, photo: &PhotoMeta) -> bool {
+        self.since.is_none_or(|since| photo.datetime >= since)
+            && self.until.is_none_or(|until| photo.datetime < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::Normalizations;
+    use crate::metadata::{DateSource, Precision};
+    use std::path::PathBuf;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+    }
+
+    fn photo(datetime: NaiveDateTime, precision: Precision) -> PhotoMeta {
+        PhotoMeta {
+            rel_path: PathBuf::from("a.jpg"),
+            datetime,
+            offset: None,
+            source: DateSource::Original,
+            precision,
+            approximate: false,
+            normalized: Normalizations::default(),
+        }
+    }
+
+    fn range(since: Option<&str>, until: Option<&str>) -> DateRange {
+        let parse = |value: &str| DateBound::parse(value).unwrap();
+        let now = at(2024, 3, 31, 12, 0, 0);
+        DateRange::new(since.map(parse), until.map(parse), now).unwrap()
+    }
+
+    #[test]
+    fn test_parse_periods() {
+        let period = |start, unit| Ok(DateBound::Period { start, unit });
+        assert_eq!(
+            DateBound::parse("2019"),
+            period(at(2019, 1, 1, 0, 0, 0), PeriodUnit::Year)
+        );
+        assert_eq!(
+            DateBound::parse("2019-07"),
+            period(at(2019, 7, 1, 0, 0, 0), PeriodUnit::Month)
+        );
+        assert_eq!(
+            DateBound::parse("2019-07-15"),
+            period(at(2019, 7, 15, 0, 0, 0), PeriodUnit::Day)
+        );
+        assert_eq!(
+            DateBound::parse("2019-07-15T10:11:12"),
+            period(at(2019, 7, 15, 10, 11, 12), PeriodUnit::Second)
+        );
+        assert_eq!(
+            DateBound::parse("2019-07-15 10:11"),
+            period(at(2019, 7, 15, 10, 11, 0), PeriodUnit::Minute)
+        );
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        assert_eq!(
+            DateBound::parse("30d"),
+            Ok(DateBound::Ago {
+                amount: 30,
+                unit: AgoUnit::Days
+            })
+        );
+        assert_eq!(
+            DateBound::parse("5y"),
+            Ok(DateBound::Ago {
+                amount: 5,
+                unit: AgoUnit::Years
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_values() {
+        for value in [
+            "",
+            "19",
+            "2019-7",
+            "2019-13",
+            "2019-02-30",
+            "2019-07-15-01",
+            "2019T10:00",
+            "d",
+            "-5d",
+            "5x",
+            "yesterday",
+            "2019-07-15T25:00",
+        ] {
+            assert!(DateBound::parse(value).is_err(), "{value:?} parsed");
+        }
+    }
+
+    #[test]
+    fn test_until_includes_the_whole_period() {
+        let until_2019 = range(None, Some("2019"));
+        assert!(until_2019.contains(&photo(at(2019, 12, 31, 23, 59, 59), Precision::Time)));
+        assert!(!until_2019.contains(&photo(at(2020, 1, 1, 0, 0, 0), Precision::Time)));
+
+        let july = range(Some("2019-07"), Some("2019-07"));
+        assert!(july.contains(&photo(at(2019, 7, 31, 18, 0, 0), Precision::Time)));
+        assert!(!july.contains(&photo(at(2019, 6, 30, 18, 0, 0), Precision::Time)));
+        assert!(!july.contains(&photo(at(2019, 8, 1, 0, 0, 0), Precision::Time)));
+    }
+
+    #[test]
+    fn test_until_time_includes_its_minute_or_second() {
+        let until_minute = range(None, Some("2019-07-15T10:11"));
+        assert!(until_minute.contains(&photo(at(2019, 7, 15, 10, 11, 59), Precision::Time)));
+        assert!(!until_minute.contains(&photo(at(2019, 7, 15, 10, 12, 0), Precision::Time)));
+
+        let until_second = range(None, Some("2019-07-15T10:11:12"));
+        assert!(until_second.contains(&photo(at(2019, 7, 15, 10, 11, 12), Precision::Time)));
+        assert!(!until_second.contains(&photo(at(2019, 7, 15, 10, 11, 13), Precision::Time)));
+    }
+
+    #[test]
+    fn test_relative_bounds_count_back_from_now() {
+        let last_month = range(Some("1m"), None);
+        assert!(last_month.contains(&photo(at(2024, 2, 29, 12, 0, 0), Precision::Time)));
+        assert!(!last_month.contains(&photo(at(2024, 2, 29, 11, 59, 59), Precision::Time)));
+
+        let older = range(None, Some("30d"));
+        assert!(older.contains(&photo(at(2024, 2, 29, 11, 0, 0), Precision::Time)));
+        assert!(!older.contains(&photo(at(2024, 3, 2, 12, 0, 0), Precision::Time)));
+    }
+
+    #[test]
+    fn test_partial_dates_compare_at_start_of_period() {
+        let year_only = photo(at(1987, 1, 1, 0, 0, 0), Precision::Year);
+        assert!(range(None, Some("1987-03")).contains(&year_only));
+        assert!(!range(Some("1987-03"), None).contains(&year_only));
+    }
+
+    #[test]
+    fn test_empty_range_is_rejected() {
+        let parse = |value: &str| Some(DateBound::parse(value).unwrap());
+        let now = at(2024, 3, 31, 12, 0, 0);
+        assert!(DateRange::new(parse("2021"), parse("2020"), now).is_err());
+        assert!(DateRange::new(parse("2020"), parse("2020"), now).is_ok());
+        assert!(DateRange::default().contains(&photo(now, Precision::Time)));
+    }
+}
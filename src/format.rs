@@ -0,0 +1,282 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+/// File formats ptime can read capture dates from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Jpeg,
+    Heif,
+    Png,
+    Webp,
+    Tiff,
+    /// Adobe Digital Negative
+    Dng,
+    /// Canon RAW 2
+    Cr2,
+    /// Nikon Electronic Format
+    Nef,
+    /// Sony Alpha RAW
+    Arw,
+    /// Olympus RAW
+    Orf,
+    /// Panasonic RAW 2
+    Rw2,
+    /// MPEG-4 video
+    Mp4,
+    /// QuickTime movie
+    Mov,
+    /// 3GPP / 3GPP2 video
+    ThreeGp,
+}
+
+/// How the EXIF block is laid out inside a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// EXIF stored in an APP1 segment
+    Jpeg,
+    /// EXIF stored as an `Exif` item of an ISO base media file
+    Isobmff,
+    /// EXIF stored in a PNG `eXIf` chunk
+    Png,
+    /// EXIF stored in a RIFF `EXIF` chunk of a WebP file
+    Webp,
+    /// The whole file is a TIFF structure, possibly with a vendor magic number
+    Tiff,
+    /// QuickTime / ISO base media movie with dates in the `moov` atom
+    QuickTime,
+}
+
+impl Container {
+    /// Whether dates are read from an EXIF block
+    pub fn has_exif(&self) -> bool {
+        !matches!(self, Container::QuickTime)
+    }
+}
+
+/// Whether a file holds a still image or a video clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Photo,
+    Video,
+}
+
+/// Media kinds selected with `--media`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum MediaFilter {
+    Photos,
+    Videos,
+    #[default]
+    All,
+}
+
+impl MediaFilter {
+    pub fn includes(&self, kind: MediaKind) -> bool {
+        match self {
+            MediaFilter::Photos => kind == MediaKind::Photo,
+            MediaFilter::Videos => kind == MediaKind::Video,
+            MediaFilter::All => true,
+        }
+    }
+}
+
+/// Known formats and their lowercase file extensions
+const REGISTRY: [(FileFormat, &[&str]); 14] = [
+    (FileFormat::Jpeg, &["jpg", "jpeg"]),
+    (FileFormat::Heif, &["heic", "heif"]),
+    (FileFormat::Png, &["png"]),
+    (FileFormat::Webp, &["webp"]),
+    (FileFormat::Tiff, &["tif", "tiff"]),
+    (FileFormat::Dng, &["dng"]),
+    (FileFormat::Cr2, &["cr2"]),
+    (FileFormat::Nef, &["nef"]),
+    (FileFormat::Arw, &["arw"]),
+    (FileFormat::Orf, &["orf"]),
+    (FileFormat::Rw2, &["rw2"]),
+    (FileFormat::Mp4, &["mp4", "m4v"]),
+    (FileFormat::Mov, &["mov", "qt"]),
+    (FileFormat::ThreeGp, &["3gp", "3g2"]),
+];
+
+impl FileFormat {
+    /// Detects the format from the file extension, case-insensitively.
+    pub fn from_path(path: &Path) -> Option<FileFormat> {
+        let ext_lower = path.extension()?.to_string_lossy().to_lowercase();
+        REGISTRY
+            .iter()
+            .find(|(_, extensions)| extensions.contains(&ext_lower.as_str()))
+            .map(|(format, _)| *format)
+    }
+
+    pub fn container(&self) -> Container {
+        match self {
+            FileFormat::Jpeg => Container::Jpeg,
+            FileFormat::Heif => Container::Isobmff,
+            FileFormat::Png => Container::Png,
+            FileFormat::Webp => Container::Webp,
+            FileFormat::Tiff
+            | FileFormat::Dng
+            | FileFormat::Cr2
+            | FileFormat::Nef
+            | FileFormat::Arw
+            | FileFormat::Orf
+            | FileFormat::Rw2 => Container::Tiff,
+            FileFormat::Mp4 | FileFormat::Mov | FileFormat::ThreeGp => Container::QuickTime,
+        }
+    }
+
+    pub fn media_kind(&self) -> MediaKind {
+        match self.container() {
+            Container::QuickTime => MediaKind::Video,
+            _ => MediaKind::Photo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path_jpeg_and_heif() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.jpg")),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.jpeg")),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.JPG")),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.JpG")),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.HEIC")),
+            Some(FileFormat::Heif)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("photo.heif")),
+            Some(FileFormat::Heif)
+        );
+    }
+
+    #[test]
+    fn test_from_path_png_and_webp() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.png")),
+            Some(FileFormat::Png)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.PNG")),
+            Some(FileFormat::Png)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.webp")),
+            Some(FileFormat::Webp)
+        );
+    }
+
+    #[test]
+    fn test_from_path_tiff_and_raw() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("scan.tif")),
+            Some(FileFormat::Tiff)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("scan.TIFF")),
+            Some(FileFormat::Tiff)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.dng")),
+            Some(FileFormat::Dng)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.CR2")),
+            Some(FileFormat::Cr2)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.nef")),
+            Some(FileFormat::Nef)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.ARW")),
+            Some(FileFormat::Arw)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.orf")),
+            Some(FileFormat::Orf)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.RW2")),
+            Some(FileFormat::Rw2)
+        );
+    }
+
+    #[test]
+    fn test_from_path_video() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("clip.MP4")),
+            Some(FileFormat::Mp4)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("clip.m4v")),
+            Some(FileFormat::Mp4)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("IMG_0001.MOV")),
+            Some(FileFormat::Mov)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("clip.3gp")),
+            Some(FileFormat::ThreeGp)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("clip.3G2")),
+            Some(FileFormat::ThreeGp)
+        );
+    }
+
+    #[test]
+    fn test_media_kind() {
+        assert_eq!(FileFormat::Jpeg.media_kind(), MediaKind::Photo);
+        assert_eq!(FileFormat::Rw2.media_kind(), MediaKind::Photo);
+        assert_eq!(FileFormat::Mov.media_kind(), MediaKind::Video);
+        assert!(!FileFormat::Mp4.container().has_exif());
+        assert!(FileFormat::Webp.container().has_exif());
+    }
+
+    #[test]
+    fn test_media_filter_includes() {
+        assert!(MediaFilter::Photos.includes(MediaKind::Photo));
+        assert!(!MediaFilter::Photos.includes(MediaKind::Video));
+        assert!(!MediaFilter::Videos.includes(MediaKind::Photo));
+        assert!(MediaFilter::Videos.includes(MediaKind::Video));
+        assert!(MediaFilter::All.includes(MediaKind::Photo));
+        assert!(MediaFilter::All.includes(MediaKind::Video));
+    }
+
+    #[test]
+    fn test_from_path_unsupported() {
+        assert_eq!(FileFormat::from_path(Path::new("photo.gif")), None);
+        assert_eq!(FileFormat::from_path(Path::new("photo.avif")), None);
+        assert_eq!(FileFormat::from_path(Path::new("photo.txt")), None);
+        assert_eq!(FileFormat::from_path(Path::new("photo")), None);
+        assert_eq!(FileFormat::from_path(Path::new("photo.cr3")), None);
+    }
+
+    #[test]
+    fn test_container_routing() {
+        assert_eq!(FileFormat::Jpeg.container(), Container::Jpeg);
+        assert_eq!(FileFormat::Heif.container(), Container::Isobmff);
+        assert_eq!(FileFormat::Png.container(), Container::Png);
+        assert_eq!(FileFormat::Webp.container(), Container::Webp);
+        assert_eq!(FileFormat::Tiff.container(), Container::Tiff);
+        assert_eq!(FileFormat::Orf.container(), Container::Tiff);
+        assert_eq!(FileFormat::Rw2.container(), Container::Tiff);
+        assert_eq!(FileFormat::Mov.container(), Container::QuickTime);
+    }
+}
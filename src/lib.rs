@@ -1,49 +1,170 @@
 pub mod analysis;
+pub mod cache;
 pub mod cli;
+pub mod container;
+pub mod datetime;
+pub mod directory;
 pub mod error;
+pub mod filename;
+pub mod filter;
+pub mod format;
 pub mod metadata;
 pub mod render;
 pub mod scanner;
+pub mod takeout;
+pub mod video;
+pub mod xmp;
 
-use cli::{Cli, CommandKind};
+use analysis::Accumulator;
+use cache::Cache;
+use cli::{CacheAction, Cli, CliCommand, CommandKind};
+use metadata::SkipReason;
+use std::path::Path;
 
 pub fn run() -> anyhow::Result<()> {
     let cmd = Cli::parse_args().map_err(|e| anyhow::anyhow!("CLI parsing error: {}", e))?;
 
-    let photos = match metadata::collect_photos(&cmd.directory) {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(e.exit_code());
-        }
-    };
-
     match cmd.kind {
-        CommandKind::Oldest => {
-            if let Some(photo) = analysis::find_oldest(&photos) {
-                println!("{} {}", photo.rel_path.display(), photo.date);
+        CommandKind::Oldest { format } => {
+            if let Some(photo) = accumulate(&cmd, analysis::Oldest::default()) {
+                println!("{}", render::render_photo(&photo, format));
             }
             // Empty output for no photos
         }
-        CommandKind::Latest => {
-            if let Some(photo) = analysis::find_latest(&photos) {
-                println!("{} {}", photo.rel_path.display(), photo.date);
+        CommandKind::Latest { format } => {
+            if let Some(photo) = accumulate(&cmd, analysis::Latest::default()) {
+                println!("{}", render::render_photo(&photo, format));
             }
             // Empty output for no photos
         }
-        CommandKind::Hist { width } => {
-            let histogram = analysis::build_histogram(&photos);
-            let lines = render::render_histogram(&histogram, width);
+        CommandKind::Hist {
+            width,
+            by_source,
+            by,
+        } => {
+            let (mut lines, unplaced) = if by_source {
+                let histogram = accumulate(&cmd, analysis::SourceHistogram::new(by));
+                let lines = render::render_source_histogram(&histogram.buckets, width);
+                (lines, histogram.unplaced)
+            } else {
+                let histogram = accumulate(&cmd, analysis::Histogram::new(by));
+                let lines = render::render_histogram(&histogram.buckets, width);
+                (lines, histogram.unplaced)
+            };
+            lines.extend(render::render_unplaced(unplaced, by));
             for line in lines {
                 println!("{}", line);
             }
             // Empty output for no photos
         }
+        CommandKind::Calendar { year } => {
+            if let Some(year_days) = accumulate(&cmd, analysis::DayCounts::new(year)) {
+                for line in render::render_calendar(&year_days) {
+                    println!("{}", line);
+                }
+            }
+            // Empty output for no photos
+        }
+        CommandKind::Punchcard => {
+            if let Some(week_hours) = accumulate(&cmd, analysis::Punchcard::default()) {
+                for line in render::render_punchcard(&week_hours) {
+                    println!("{}", line);
+                }
+            }
+            // Empty output for no photos
+        }
+        CommandKind::Stats => {
+            let summary = accumulate(&cmd, analysis::Stats::default());
+            for line in render::render_stats(&summary) {
+                println!("{}", line);
+            }
+            // Empty output for no files
+        }
+        CommandKind::Cache(action) => {
+            let path = cmd
+                .cache
+                .as_deref()
+                .expect("cache command has a cache path");
+            if let Err(e) = maintain_cache(path, action) {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Streams every dated photo of the scan into `accumulator`, normalized to
+/// `--tz` when given and limited to `--since`/`--until`, and reports the
+/// other files as skipped. Exits with the error's code if the scan fails.
+fn accumulate<A: Accumulator>(cmd: &CliCommand, mut accumulator: A) -> A::Output {
+    // A broken cache only costs speed, so it never fails the scan
+    let cache = cmd.cache.as_deref().and_then(|path| {
+        match Cache::load(path, &cmd.options, &cmd.directory) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!("Warning: not using cache {}: {}", path.display(), e);
+                None
+            }
+        }
+    });
+    let scanned =
+        metadata::for_each_file(
+            &cmd.directory,
+            &cmd.options,
+            cache.as_ref(),
+            |file| match file {
+                Ok(mut photo) => {
+                    if let Some(zone) = cmd.tz {
+                        photo.normalize_to(zone);
+                    }
+                    if cmd.range.contains(&photo) {
+                        accumulator.add(photo);
+                    } else {
+                        accumulator.skip(SkipReason::OutOfRange);
+                    }
+                }
+                Err(reason) => accumulator.skip(reason),
+            },
+        );
+    if let Err(e) = scanned {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+    if let Some(cache) = cache {
+        if let Err(e) = cache.save() {
+            eprintln!(
+                "Warning: could not save cache {}: {}",
+                cache.path().display(),
+                e
+            );
+        }
+    }
+    accumulator.finish()
+}
+
+/// Runs `ptime cache prune` or `ptime cache clear` on the cache at `path`.
+fn maintain_cache(path: &Path, action: CacheAction) -> Result<(), error::PtimeError> {
+    match action {
+        CacheAction::Prune => {
+            let (removed, kept) = cache::prune(path)?;
+            println!(
+                "Removed {} stale {}, {} kept",
+                removed,
+                if removed == 1 { "entry" } else { "entries" },
+                kept
+            );
+        }
+        CacheAction::Clear => {
+            if cache::clear(path)? {
+                println!("Removed {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     // The run() function now requires CLI args, so we can't test it in isolation.
@@ -1,59 +1,763 @@
+use crate::cache::Cache;
+use crate::container;
+use crate::container::JpegApp1;
+use crate::datetime::{parse_exif_datetime, Normalizations, ParseMode, ParsedDateTime};
+use crate::directory::{self, DirPattern};
 use crate::error::PtimeError;
-use crate::scanner::scan_candidates;
-use chrono::NaiveDate;
+use crate::filename::{self, FilenamePattern};
+use crate::format::{Container, FileFormat, MediaFilter};
+use crate::scanner::{scan, FoundFile, SidecarNames};
+use crate::takeout;
+use crate::video::{self, VideoDates};
+use crate::xmp::{self, XmpDate};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike};
+use clap::ValueEnum;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PhotoMeta {
     pub rel_path: PathBuf,
-    pub date: NaiveDate,
+    pub datetime: NaiveDateTime,
+    /// UTC offset of `datetime`, when the file records one
+    pub offset: Option<FixedOffset>,
+    /// Metadata field the capture date was read from
+    pub source: DateSource,
+    /// How much of `datetime` is actually known
+    pub precision: Precision,
+    /// Date is an estimate, e.g. from a `ca. 1987` directory
+    pub approximate: bool,
+    /// Repairs the lenient parser made to a malformed EXIF value
+    pub normalized: Normalizations,
 }
 
-pub fn read_capture_date(path: &Path) -> Result<Option<NaiveDate>, PtimeError> {
+impl PhotoMeta {
+    pub fn new(rel_path: PathBuf, capture: CaptureDate) -> Self {
+        PhotoMeta {
+            rel_path,
+            datetime: capture.datetime,
+            offset: capture.offset,
+            source: capture.source,
+            precision: capture.precision,
+            approximate: capture.approximate,
+            normalized: capture.normalized,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.datetime.date()
+    }
+
+    /// Shifts the capture time into `zone`. Photos without a recorded
+    /// offset are assumed to already be in that zone and are left as is.
+    pub fn normalize_to(&mut self, zone: TargetZone) {
+        if let Some(offset) = self.offset {
+            let utc = self.datetime - offset;
+            let target = zone.offset_at(&utc);
+            self.datetime = utc + target;
+            self.offset = Some(target);
+        }
+    }
+}
+
+/// Why a scanned file contributes no photo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SkipReason {
+    /// No date source yielded a date
+    NoDate,
+    /// Metadata is present but could not be parsed
+    Unreadable,
+    /// Dated, but outside `--since`/`--until`
+    OutOfRange,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::NoDate => "no date",
+            SkipReason::Unreadable => "unreadable metadata",
+            SkipReason::OutOfRange => "outside date range",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureDate {
+    pub datetime: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+    pub source: DateSource,
+    pub precision: Precision,
+    pub approximate: bool,
+    pub normalized: Normalizations,
+}
+
+/// How much of a capture date is known. Partial dates are stored as the
+/// first instant of the period they cover, e.g. 1987-01-01T00:00 for 1987.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Precision {
+    Year,
+    Month,
+    Day,
+    /// Date and time of day
+    #[default]
+    Time,
+}
+
+/// Metadata field a capture date can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum DateSource {
+    /// XMP sidecar, or the XMP packet embedded in a JPEG
+    Xmp,
+    /// EXIF `DateTimeOriginal`
+    Original,
+    /// Google Takeout `photoTakenTime` from a JSON sidecar, always UTC
+    Takeout,
+    /// EXIF `DateTime`, rewritten by most editors on save
+    Modify,
+    /// EXIF `DateTimeDigitized`
+    Digitized,
+    /// EXIF `GPSDateStamp` + `GPSTimeStamp`, always UTC
+    Gps,
+    /// QuickTime `com.apple.quicktime.creationdate`, local time with offset
+    #[value(name = "creationdate")]
+    CreationDate,
+    /// QuickTime/MP4 movie header (`mvhd`) creation time, always UTC
+    Mvhd,
+    /// Date encoded in the file name, e.g. `IMG_20190715_101112.jpg`
+    Filename,
+    /// Year, month or day from the names of the enclosing directories
+    Dir,
+    /// File system modification time, only with `--fallback mtime`
+    Mtime,
+    /// File system birth time (`statx` on Linux), only with `--fallback btime`
+    Btime,
+}
+
+/// Sources consulted when the user does not ask for anything else. XMP comes
+/// first because editors record corrected capture times there, and Takeout
+/// sidecars beat the weaker EXIF fields that Google Photos often strips or
+/// rewrites. EXIF sources only apply to photos and QuickTime sources only to
+/// videos. File names are the last resort, once embedded metadata is
+/// exhausted. Directory names are too coarse a guess to be used unasked.
+pub const DEFAULT_SOURCES: [DateSource; 8] = [
+    DateSource::Xmp,
+    DateSource::Original,
+    DateSource::Takeout,
+    DateSource::Modify,
+    DateSource::Digitized,
+    DateSource::CreationDate,
+    DateSource::Mvhd,
+    DateSource::Filename,
+];
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Same spelling as accepted by --date-source
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl DateSource {
+    /// File system timestamps say when a file was copied or touched, which
+    /// may or may not be when it was captured.
+    pub fn is_low_confidence(&self) -> bool {
+        matches!(self, DateSource::Mtime | DateSource::Btime)
+    }
+
+    /// Date, sub-second and offset tags backing an EXIF datetime source
+    fn exif_tags(&self) -> Option<(exif::Tag, exif::Tag, exif::Tag)> {
+        match self {
+            DateSource::Original => Some((
+                exif::Tag::DateTimeOriginal,
+                exif::Tag::SubSecTimeOriginal,
+                exif::Tag::OffsetTimeOriginal,
+            )),
+            DateSource::Modify => Some((
+                exif::Tag::DateTime,
+                exif::Tag::SubSecTime,
+                exif::Tag::OffsetTime,
+            )),
+            DateSource::Digitized => Some((
+                exif::Tag::DateTimeDigitized,
+                exif::Tag::SubSecTimeDigitized,
+                exif::Tag::OffsetTimeDigitized,
+            )),
+            _ => None,
+        }
+    }
+
+    fn read(&self, probe: &mut Probe) -> Result<Option<CaptureDate>, PtimeError> {
+        match self {
+            DateSource::Xmp => Ok(probe.xmp()?.map(|date| CaptureDate {
+                datetime: date.datetime,
+                offset: date.offset,
+                source: *self,
+                precision: date.precision,
+                approximate: false,
+                normalized: Normalizations::default(),
+            })),
+            DateSource::Takeout => {
+                Ok(
+                    read_takeout(probe.path, probe.sidecars)?.map(|datetime| CaptureDate {
+                        datetime,
+                        offset: FixedOffset::east_opt(0),
+                        source: *self,
+                        precision: Precision::Time,
+                        approximate: false,
+                        normalized: Normalizations::default(),
+                    }),
+                )
+            }
+            DateSource::Filename => Ok(probe
+                .path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| {
+                    filename::date_from_filename(name, &probe.options.filename_patterns)
+                })
+                .map(|(datetime, precision)| CaptureDate {
+                    datetime,
+                    offset: None,
+                    source: *self,
+                    precision,
+                    approximate: false,
+                    normalized: Normalizations::default(),
+                })),
+            DateSource::Dir => Ok(directory::date_from_dirs(
+                probe.rel_path,
+                &probe.options.dir_patterns,
+            )
+            .map(|dir_date| CaptureDate {
+                datetime: dir_date.date.and_time(NaiveTime::MIN),
+                offset: None,
+                source: *self,
+                precision: dir_date.precision,
+                approximate: dir_date.approximate,
+                normalized: Normalizations::default(),
+            })),
+            DateSource::Mtime => read_file_time(probe.path, std::fs::Metadata::modified, *self),
+            DateSource::Btime => read_file_time(probe.path, std::fs::Metadata::created, *self),
+            DateSource::Gps => {
+                let mode = probe.options.parse_mode;
+                Ok(probe.exif()?.and_then(|exif| read_gps_datetime(exif, mode)))
+            }
+            DateSource::CreationDate => Ok(probe.video()?.and_then(|dates| {
+                dates.apple_creation_date.map(|datetime| CaptureDate {
+                    datetime: datetime.naive_local(),
+                    offset: Some(*datetime.offset()),
+                    source: *self,
+                    precision: Precision::Time,
+                    approximate: false,
+                    normalized: Normalizations::default(),
+                })
+            })),
+            DateSource::Mvhd => Ok(probe.video()?.and_then(|dates| {
+                dates.movie_header.map(|datetime| CaptureDate {
+                    datetime,
+                    offset: FixedOffset::east_opt(0),
+                    source: *self,
+                    precision: Precision::Time,
+                    approximate: false,
+                    normalized: Normalizations::default(),
+                })
+            })),
+            _ => {
+                let mode = probe.options.parse_mode;
+                Ok(probe
+                    .exif()?
+                    .and_then(|exif| self.read_exif_datetime(exif, mode)))
+            }
+        }
+    }
+
+    fn read_exif_datetime(&self, exif: &exif::Exif, mode: ParseMode) -> Option<CaptureDate> {
+        match self.exif_tags() {
+            Some((tag, subsec_tag, offset_tag)) => {
+                let parsed = exif
+                    .fields()
+                    .filter(|field| field.tag == tag)
+                    .find_map(|field| extract_date_from_field(field, mode))?;
+                let mut capture = CaptureDate {
+                    datetime: parsed.datetime,
+                    offset: None,
+                    source: *self,
+                    precision: parsed.precision,
+                    approximate: false,
+                    normalized: parsed.normalizations,
+                };
+                if parsed.precision < Precision::Time {
+                    return Some(capture);
+                }
+                // The dedicated tags win over a fraction or zone written
+                // into the value by non-conforming software
+                let nanos = exif
+                    .fields()
+                    .filter(|field| field.tag == subsec_tag)
+                    .find_map(|field| extract_ascii_field(field, parse_exif_subsec));
+                if let Some(datetime) = nanos.and_then(|n| parsed.datetime.with_nanosecond(n)) {
+                    capture.datetime = datetime;
+                }
+                capture.offset = exif
+                    .fields()
+                    .filter(|field| field.tag == offset_tag)
+                    .find_map(|field| extract_ascii_field(field, parse_utc_offset))
+                    .or(parsed.offset);
+                Some(capture)
+            }
+            None => None,
+        }
+    }
+}
+
+/// Metadata of a single file, loaded on first use by the sources that need
+/// it. A source that does not apply to the file's container finds nothing.
+struct Probe<'a> {
+    path: &'a Path,
+    /// Path relative to the scan root, whose directories may hold a date
+    rel_path: &'a Path,
+    format: FileFormat,
+    sidecars: &'a SidecarNames,
+    options: &'a ScanOptions,
+    /// APP1 segments of a JPEG, found in one walk for the EXIF and XMP
+    /// sources alike
+    jpeg: Option<JpegApp1>,
+    exif: Option<Option<exif::Exif>>,
+    video: Option<Option<VideoDates>>,
+    xmp: Option<Option<XmpDate>>,
+    /// First non-fatal read error, reported when no source yields a date
+    failure: Option<PtimeError>,
+}
+
+impl<'a> Probe<'a> {
+    fn new(found: &'a FoundFile, options: &'a ScanOptions) -> Self {
+        Probe {
+            path: &found.abs_path,
+            rel_path: &found.rel_path,
+            format: found.format,
+            sidecars: &found.sidecars,
+            options,
+            jpeg: None,
+            exif: None,
+            video: None,
+            xmp: None,
+            failure: None,
+        }
+    }
+
+    fn jpeg(&mut self) -> Result<&mut JpegApp1, PtimeError> {
+        if self.jpeg.is_none() {
+            let sources = &self.options.sources;
+            let exif = sources
+                .iter()
+                .any(|source| source.exif_tags().is_some() || *source == DateSource::Gps);
+            let xmp = sources.contains(&DateSource::Xmp);
+            let found = match read_jpeg(self.path, exif, xmp) {
+                // A broken JPEG is reported by the EXIF sources, if any
+                Err(PtimeError::Exif(_)) if !exif => None,
+                result => self.keep_failure(result)?,
+            };
+            self.jpeg = Some(found.unwrap_or_default());
+        }
+        Ok(self.jpeg.get_or_insert_with(JpegApp1::default))
+    }
+
+    fn exif(&mut self) -> Result<Option<&exif::Exif>, PtimeError> {
+        if self.exif.is_none() {
+            let loaded = match self.format.container() {
+                Container::Jpeg => match self.jpeg()?.exif.take() {
+                    Some(payload) => {
+                        let parsed = parse_exif(self.path, exif::Reader::new().read_raw(payload));
+                        self.keep_failure(parsed)?.flatten()
+                    }
+                    None => None,
+                },
+                container if container.has_exif() => self
+                    .keep_failure(read_exif(self.path, self.format))?
+                    .flatten(),
+                _ => None,
+            };
+            self.exif = Some(loaded);
+        }
+        Ok(self.exif.as_ref().and_then(Option::as_ref))
+    }
+
+    fn video(&mut self) -> Result<Option<&VideoDates>, PtimeError> {
+        if self.video.is_none() {
+            let loaded = if self.format.container() == Container::QuickTime {
+                self.keep_failure(read_video(self.path))?
+            } else {
+                None
+            };
+            self.video = Some(loaded);
+        }
+        Ok(self.video.as_ref().and_then(Option::as_ref))
+    }
+
+    /// Reads the capture date from an XMP sidecar, falling back to the
+    /// packet embedded in a JPEG. A sidecar is only consulted when its
+    /// packet holds a date, so an empty Lightroom sidecar does not hide the
+    /// embedded one.
+    fn xmp(&mut self) -> Result<Option<XmpDate>, PtimeError> {
+        if self.xmp.is_none() {
+            let mut date = read_xmp_sidecar(self.path, self.sidecars)?;
+            if date.is_none() && self.format.container() == Container::Jpeg {
+                date = self
+                    .jpeg()?
+                    .xmp
+                    .as_deref()
+                    .and_then(|packet| xmp::read_xmp_date(&String::from_utf8_lossy(packet)));
+            }
+            self.xmp = Some(date);
+        }
+        Ok(self.xmp.flatten())
+    }
+
+    /// IO errors abort the scan; any other error is remembered and the
+    /// metadata treated as absent so later sources still get a chance.
+    fn keep_failure<T>(&mut self, result: Result<T, PtimeError>) -> Result<Option<T>, PtimeError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(PtimeError::Io(e)) => Err(PtimeError::Io(e)),
+            Err(e) => {
+                self.failure.get_or_insert(e);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Options controlling how capture dates are collected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    /// Sources to try for each file, in priority order
+    pub sources: Vec<DateSource>,
+    /// Kinds of media to include in the scan
+    pub media: MediaFilter,
+    /// User patterns tried before the built-in ones by the `filename` source
+    pub filename_patterns: Vec<FilenamePattern>,
+    /// User patterns tried before the built-in ones by the `dir` source
+    pub dir_patterns: Vec<DirPattern>,
+    /// How malformed EXIF date values are handled
+    pub parse_mode: ParseMode,
+    /// Worker threads reading files in parallel
+    pub jobs: NonZeroUsize,
+}
+
+impl ScanOptions {
+    /// Builds options from an explicit source chain. Duplicates are dropped,
+    /// keeping the first occurrence; an empty chain means the default one.
+    pub fn with_sources(sources: &[DateSource]) -> Self {
+        if sources.is_empty() {
+            return ScanOptions::default();
+        }
+
+        let mut chain = Vec::with_capacity(sources.len());
+        for source in sources {
+            if !chain.contains(source) {
+                chain.push(*source);
+            }
+        }
+        ScanOptions {
+            sources: chain,
+            ..ScanOptions::default()
+        }
+    }
+
+    /// Moves `source` to the front of the chain, adding it if absent.
+    pub fn prefer(&mut self, source: DateSource) {
+        self.sources.retain(|s| *s != source);
+        self.sources.insert(0, source);
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            sources: DEFAULT_SOURCES.to_vec(),
+            media: MediaFilter::default(),
+            filename_patterns: Vec::new(),
+            dir_patterns: Vec::new(),
+            parse_mode: ParseMode::default(),
+            jobs: default_jobs(),
+        }
+    }
+}
+
+/// One worker per available CPU.
+pub fn default_jobs() -> NonZeroUsize {
+    std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Time zone that capture times are normalized to before analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetZone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl TargetZone {
+    /// Current wall-clock time in this zone.
+    pub fn now(&self) -> NaiveDateTime {
+        let utc = chrono::Utc::now().naive_utc();
+        utc + self.offset_at(&utc)
+    }
+
+    fn offset_at(&self, utc: &NaiveDateTime) -> FixedOffset {
+        match self {
+            TargetZone::Utc => FixedOffset::east_opt(0).unwrap(),
+            TargetZone::Local => chrono::Local.offset_from_utc_datetime(utc).fix(),
+            TargetZone::Fixed(offset) => *offset,
+        }
+    }
+}
+
+pub fn read_capture_date(
+    found: &FoundFile,
+    options: &ScanOptions,
+) -> Result<Option<CaptureDate>, PtimeError> {
+    let mut probe = Probe::new(found, options);
+
+    // First source in priority order that yields a valid date wins
+    for source in &options.sources {
+        if let Some(capture) = source.read(&mut probe)? {
+            return Ok(Some(capture));
+        }
+    }
+    match probe.failure {
+        Some(err) => Err(err),
+        None => Ok(None),
+    }
+}
+
+/// Reads the capture date from the first XMP sidecar of `path` in
+/// `sidecars` whose packet holds one.
+fn read_xmp_sidecar(path: &Path, sidecars: &SidecarNames) -> Result<Option<XmpDate>, PtimeError> {
+    for sidecar in xmp::sidecar_paths(path) {
+        if !sidecars.contains(&sidecar) {
+            continue;
+        }
+        match std::fs::read(&sidecar) {
+            Ok(packet) => {
+                if let Some(date) = xmp::read_xmp_date(&String::from_utf8_lossy(&packet)) {
+                    return Ok(Some(date));
+                }
+            }
+            // Deleted since the directory was listed
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(PtimeError::Io(e)),
+        }
+    }
+    Ok(None)
+}
+
+/// Reads `photoTakenTime` from the first Google Takeout sidecar of `path`
+/// in `sidecars`.
+fn read_takeout(path: &Path, sidecars: &SidecarNames) -> Result<Option<NaiveDateTime>, PtimeError> {
+    for sidecar in takeout::sidecar_paths(path) {
+        if !sidecars.contains(&sidecar) {
+            continue;
+        }
+        match std::fs::read(&sidecar) {
+            Ok(json) => {
+                return Ok(takeout::read_photo_taken_time(&String::from_utf8_lossy(
+                    &json,
+                )))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(PtimeError::Io(e)),
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a file system timestamp as local time. Platforms and file systems
+/// that do not record it yield no date.
+fn read_file_time(
+    path: &Path,
+    time: fn(&std::fs::Metadata) -> std::io::Result<std::time::SystemTime>,
+    source: DateSource,
+) -> Result<Option<CaptureDate>, PtimeError> {
+    let metadata = std::fs::metadata(path)?;
+    let Ok(time) = time(&metadata) else {
+        return Ok(None);
+    };
+    let local = chrono::DateTime::<chrono::Local>::from(time);
+    Ok(Some(CaptureDate {
+        datetime: local.naive_local(),
+        offset: Some(local.offset().fix()),
+        source,
+        precision: Precision::Time,
+        approximate: false,
+        normalized: Normalizations::default(),
+    }))
+}
+
+fn read_video(path: &Path) -> Result<VideoDates, PtimeError> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
 
-    let exif_reader = exif::Reader::new();
-    let exif = exif_reader.read_from_container(&mut reader).map_err(|e| {
-        PtimeError::Exif(format!(
+    video::read_video_dates(&mut reader).map_err(|e| {
+        if is_malformed(&e) {
+            PtimeError::Video(format!(
+                "Failed to read video dates from {}: {}",
+                path.display(),
+                e
+            ))
+        } else {
+            PtimeError::Io(e)
+        }
+    })
+}
+
+/// Whether an IO error stems from a malformed or truncated file rather than
+/// from the file system.
+fn is_malformed(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof
+    )
+}
+
+/// Walks the segments of a JPEG file once for the APP1 payloads asked for.
+fn read_jpeg(path: &Path, exif: bool, xmp: bool) -> Result<JpegApp1, PtimeError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match container::read_jpeg_app1(&mut reader, exif, xmp) {
+        Ok(found) => Ok(found),
+        Err(e) if is_malformed(&e) => Err(PtimeError::Exif(format!(
             "Failed to read EXIF from {}: {}",
             path.display(),
             e
-        ))
-    })?;
+        ))),
+        Err(e) => Err(PtimeError::Io(e)),
+    }
+}
 
-    // Try fields in order: DateTimeOriginal, CreateDate, ModifyDate
-    let field_tags = [
-        exif::Tag::DateTimeOriginal,
-        exif::Tag::DateTime,
-        exif::Tag::DateTimeDigitized,
-    ];
+/// Reads the EXIF block of a still image in any container but JPEG, whose
+/// segments the probe walks itself. A file without one is `None`; a
+/// damaged one is an error.
+fn read_exif(path: &Path, format: FileFormat) -> Result<Option<exif::Exif>, PtimeError> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
 
-    for tag in &field_tags {
-        if let Some(date) = exif
-            .fields()
-            .filter(|field| field.tag == *tag)
-            .find_map(extract_date_from_field)
-        {
-            return Ok(Some(date));
+    let exif_reader = exif::Reader::new();
+    let result = match format.container() {
+        Container::Isobmff => exif_reader.read_from_container(&mut reader),
+        Container::Tiff => {
+            let Some(payload) = read_chunk_payload(path, container::read_tiff_exif(&mut reader))?
+            else {
+                return Ok(None);
+            };
+            exif_reader.read_raw(payload)
+        }
+        Container::Png => {
+            let Some(payload) = read_chunk_payload(path, container::read_png_exif(&mut reader))?
+            else {
+                return Ok(None);
+            };
+            exif_reader.read_raw(payload)
         }
+        Container::Webp => {
+            let Some(payload) = read_chunk_payload(path, container::read_webp_exif(&mut reader))?
+            else {
+                return Ok(None);
+            };
+            exif_reader.read_raw(payload)
+        }
+        Container::Jpeg | Container::QuickTime => return Ok(None),
+    };
+    parse_exif(path, result)
+}
+
+/// Maps the outcome of the EXIF parser: no EXIF data is `None`.
+fn parse_exif(
+    path: &Path,
+    result: Result<exif::Exif, exif::Error>,
+) -> Result<Option<exif::Exif>, PtimeError> {
+    match result {
+        Ok(exif) => Ok(Some(exif)),
+        Err(exif::Error::NotFound(_)) => Ok(None),
+        Err(e) => Err(PtimeError::Exif(format!(
+            "Failed to read EXIF from {}: {}",
+            path.display(),
+            e
+        ))),
     }
+}
 
-    // No valid date found
-    Ok(None)
+/// Maps the outcome of a chunk or segment walker: malformed or truncated
+/// files are treated like unreadable EXIF, other IO errors propagate.
+fn read_chunk_payload(
+    path: &Path,
+    result: std::io::Result<Option<Vec<u8>>>,
+) -> Result<Option<Vec<u8>>, PtimeError> {
+    match result {
+        Ok(payload) => Ok(payload),
+        Err(e) if is_malformed(&e) => Err(PtimeError::Exif(format!(
+            "Failed to read EXIF from {}: {}",
+            path.display(),
+            e
+        ))),
+        Err(e) => Err(PtimeError::Io(e)),
+    }
+}
+
+fn read_gps_datetime(exif: &exif::Exif, mode: ParseMode) -> Option<CaptureDate> {
+    let date = exif
+        .fields()
+        .filter(|field| field.tag == exif::Tag::GPSDateStamp)
+        .find_map(|field| extract_date_from_field(field, mode))
+        .filter(|parsed| parsed.precision >= Precision::Day)?
+        .datetime
+        .date();
+    let time = exif
+        .fields()
+        .filter(|field| field.tag == exif::Tag::GPSTimeStamp)
+        .find_map(extract_gps_time_from_field)?;
+
+    Some(CaptureDate {
+        datetime: date.and_time(time),
+        offset: FixedOffset::east_opt(0),
+        source: DateSource::Gps,
+        precision: Precision::Time,
+        approximate: false,
+        normalized: Normalizations::default(),
+    })
+}
+
+fn extract_gps_time_from_field(field: &exif::Field) -> Option<NaiveTime> {
+    // GPSTimeStamp is an hour, minute, second rational triple in UTC
+    if let exif::Value::Rational(ref values) = field.value {
+        if values.len() != 3 || values.iter().any(|v| v.denom == 0) {
+            return None;
+        }
+        let hour = values[0].to_f64();
+        let minute = values[1].to_f64();
+        let second = values[2].to_f64();
+        let whole_second = second.trunc();
+        let nanos = ((second - whole_second) * 1e9).round() as u32;
+        return NaiveTime::from_hms_nano_opt(
+            hour as u32,
+            minute as u32,
+            whole_second as u32,
+            nanos.min(999_999_999),
+        );
+    }
+    None
 }
 
-fn extract_date_from_field(field: &exif::Field) -> Option<NaiveDate> {
+fn extract_date_from_field(field: &exif::Field, mode: ParseMode) -> Option<ParsedDateTime> {
     if let exif::Value::Ascii(ref values) = field.value {
         for raw in values {
             if let Ok(text) = std::str::from_utf8(raw) {
-                let trimmed = text.trim_matches('\0').trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                if let Some(date) = parse_exif_datetime(trimmed) {
+                if let Some(date) = parse_exif_datetime(text, mode) {
                     return Some(date);
                 }
             }
@@ -62,110 +766,665 @@ fn extract_date_from_field(field: &exif::Field) -> Option<NaiveDate> {
     None
 }
 
-fn parse_exif_datetime(datetime_str: &str) -> Option<NaiveDate> {
-    // EXIF datetime format: "YYYY:MM:DD HH:MM:SS"
-    // or sometimes just "YYYY:MM:DD". Some readers normalize to hyphen.
-    let parts: Vec<&str> = datetime_str.split_whitespace().collect();
-    if parts.is_empty() {
+fn extract_ascii_field<T>(field: &exif::Field, parse: fn(&str) -> Option<T>) -> Option<T> {
+    if let exif::Value::Ascii(ref values) = field.value {
+        for raw in values {
+            if let Ok(text) = std::str::from_utf8(raw) {
+                if let Some(value) = parse(text.trim_matches('\0').trim()) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parses a UTC offset such as `+09:00`, `-0530`, `+02` or `Z`.
+/// An optional `UTC`/`GMT` prefix is accepted, e.g. `UTC+9`.
+pub fn parse_utc_offset(offset_str: &str) -> Option<FixedOffset> {
+    let upper = offset_str.trim().to_ascii_uppercase();
+    let rest = upper
+        .strip_prefix("UTC")
+        .or_else(|| upper.strip_prefix("GMT"))
+        .unwrap_or(&upper);
+    if rest == "Z" || (rest.is_empty() && upper.len() == 3) {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, digits) = match rest.as_bytes().first()? {
+        b'+' => (1, &rest[1..]),
+        b'-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    if hours.is_empty() || hours.len() > 2 || minutes.len() > 2 {
+        return None;
+    }
+    let hours = hours.parse::<i32>().ok()?;
+    let minutes = minutes.parse::<i32>().ok()?;
+    if hours > 23 || minutes > 59 {
         return None;
     }
 
-    let date_part = parts[0];
-    let separators = [':', '-'];
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
 
-    for sep in separators {
-        let date_components: Vec<&str> = date_part.split(sep).collect();
-        if date_components.len() != 3 {
-            continue;
+fn parse_exif_subsec(subsec_str: &str) -> Option<u32> {
+    // SubSecTime* tags hold the decimal digits following the seconds,
+    // e.g. "26" means .26 seconds. Only nanosecond precision is kept.
+    if subsec_str.is_empty() || !subsec_str.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits: String = subsec_str
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(9)
+        .collect();
+    digits.parse::<u32>().ok()
+}
+
+/// Files read per worker thread before their photos are handed on. Bounds
+/// memory use on huge trees while keeping every worker busy.
+const BATCH_PER_JOB: usize = 64;
+
+/// Streams every dated photo under `root` to `visit`, in scan order.
+/// Files without a date are skipped; see [`for_each_file`].
+pub fn for_each_photo(
+    root: &Path,
+    options: &ScanOptions,
+    cache: Option<&Cache>,
+    mut visit: impl FnMut(PhotoMeta),
+) -> Result<(), PtimeError> {
+    for_each_file(root, options, cache, |file| {
+        if let Ok(photo) = file {
+            visit(photo);
         }
+    })
+}
 
-        let year = match date_components[0].parse::<i32>() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let month = match date_components[1].parse::<u32>() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let day = match date_components[2].parse::<u32>() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+/// Streams every file under `root` that `options.media` selects to
+/// `visit`, in scan order, as its photo or the reason it has none.
+///
+/// Files are read in batches on `options.jobs` threads, so only one batch
+/// is held in memory at a time. Files whose date is in `cache` are not
+/// opened. IO errors stop the scan.
+pub fn for_each_file(
+    root: &Path,
+    options: &ScanOptions,
+    cache: Option<&Cache>,
+    mut visit: impl FnMut(Result<PhotoMeta, SkipReason>),
+) -> Result<(), PtimeError> {
+    let batch_size = options.jobs.get() * BATCH_PER_JOB;
+    let mut candidates = scan(root)?.filter(|found| match found {
+        Ok(found) => options.media.includes(found.format.media_kind()),
+        Err(_) => true,
+    });
+    let mut batch = Vec::with_capacity(batch_size);
 
-        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
-            return Some(date);
+    loop {
+        for found in candidates.by_ref().take(batch_size) {
+            batch.push(found?);
+        }
+        if batch.is_empty() {
+            return Ok(());
         }
-    }
 
-    None
+        let captures = read_capture_dates(&batch, options, cache)?;
+        for (found, capture) in batch.drain(..).zip(captures) {
+            visit(capture.map(|capture| PhotoMeta::new(found.rel_path, capture)));
+        }
+    }
 }
 
-pub fn collect_photos(root: &Path) -> Result<Vec<PhotoMeta>, PtimeError> {
-    let candidates = scan_candidates(root)?;
+/// Collects every dated photo under `root`; see [`for_each_photo`].
+pub fn collect_photos(root: &Path, options: &ScanOptions) -> Result<Vec<PhotoMeta>, PtimeError> {
     let mut photos = Vec::new();
+    for_each_photo(root, options, None, |photo| photos.push(photo))?;
+    Ok(photos)
+}
 
-    for found in candidates {
-        // Try to read capture date, skip if not found or error
-        match read_capture_date(&found.abs_path) {
-            Ok(Some(date)) => {
-                photos.push(PhotoMeta {
-                    rel_path: found.rel_path,
-                    date,
-                });
-            }
-            Ok(None) => {
-                // No date found, skip silently
+/// Reads the capture date of every file on up to `options.jobs` threads.
+/// Results keep the order of `files`, so the output never depends on how
+/// work was scheduled. Dates found in `cache` are used as is. Only IO
+/// errors are returned; once one occurs the workers stop and the error of
+/// the earliest file among those read wins.
+fn read_capture_dates(
+    files: &[FoundFile],
+    options: &ScanOptions,
+    cache: Option<&Cache>,
+) -> Result<Vec<Result<CaptureDate, SkipReason>>, PtimeError> {
+    let read_file = |found: &FoundFile| match read_capture_date(found, options) {
+        Ok(capture) => Ok(capture.ok_or(SkipReason::NoDate)),
+        Err(err @ PtimeError::Io(_)) => Err(err),
+        // EXIF parsing or metadata issues are non-fatal
+        Err(_) => Ok(Err(SkipReason::Unreadable)),
+    };
+    let read = |found: &FoundFile| match cache {
+        Some(cache) => cache.get_or_read(found, || read_file(found)),
+        None => read_file(found),
+    };
+    let jobs = options.jobs.get().min(files.len());
+    if jobs <= 1 {
+        return files.iter().map(read).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let worker = || {
+        let mut done = Vec::new();
+        while !failed.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(found) = files.get(index) else {
+                break;
+            };
+            let result = read(found);
+            if result.is_err() {
+                failed.store(true, Ordering::Relaxed);
             }
-            Err(err) => {
-                if matches!(err, PtimeError::Io(_)) {
-                    return Err(err);
+            done.push((index, result));
+        }
+        done
+    };
+
+    let mut captures = vec![Err(SkipReason::NoDate); files.len()];
+    let mut first_error: Option<(usize, PtimeError)> = None;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs).map(|_| scope.spawn(worker)).collect();
+        for handle in workers {
+            let done = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in done {
+                match result {
+                    Ok(capture) => captures[index] = capture,
+                    Err(err) if first_error.as_ref().is_none_or(|(i, _)| index < *i) => {
+                        first_error = Some((index, err));
+                    }
+                    Err(_) => {}
                 }
-                // EXIF parsing or metadata issues are non-fatal
             }
         }
-    }
+    });
 
-    Ok(photos)
+    match first_error {
+        Some((_, err)) => Err(err),
+        None => Ok(captures),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+
+    fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_exif_subsec() {
+        assert_eq!(parse_exif_subsec("26"), Some(260_000_000));
+        assert_eq!(parse_exif_subsec("123456"), Some(123_456_000));
+        assert_eq!(parse_exif_subsec("0123456789"), Some(12_345_678));
+        assert_eq!(parse_exif_subsec(""), None);
+        assert_eq!(parse_exif_subsec("12a"), None);
+    }
+
+    fn offset(secs: i32) -> FixedOffset {
+        FixedOffset::east_opt(secs).unwrap()
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("+09:00"), Some(offset(9 * 3600)));
+        assert_eq!(parse_utc_offset("-05:30"), Some(offset(-(5 * 3600 + 1800))));
+        assert_eq!(parse_utc_offset("+0530"), Some(offset(5 * 3600 + 1800)));
+        assert_eq!(parse_utc_offset("-02"), Some(offset(-2 * 3600)));
+        assert_eq!(parse_utc_offset("Z"), Some(offset(0)));
+        assert_eq!(parse_utc_offset("UTC"), Some(offset(0)));
+        assert_eq!(parse_utc_offset("UTC+9"), Some(offset(9 * 3600)));
+        assert_eq!(parse_utc_offset("gmt-3"), Some(offset(-3 * 3600)));
+    }
+
+    #[test]
+    fn test_parse_utc_offset_invalid() {
+        assert!(parse_utc_offset("").is_none());
+        assert!(parse_utc_offset("   :  ").is_none());
+        assert!(parse_utc_offset("09:00").is_none());
+        assert!(parse_utc_offset("+24:00").is_none());
+        assert!(parse_utc_offset("+09:75").is_none());
+        assert!(parse_utc_offset("Tokyo").is_none());
+    }
+
+    fn make_photo(datetime: NaiveDateTime, offset: Option<FixedOffset>) -> PhotoMeta {
+        PhotoMeta {
+            rel_path: PathBuf::from("a.jpg"),
+            datetime,
+            offset,
+            source: DateSource::Original,
+            precision: Precision::Time,
+            approximate: false,
+            normalized: Normalizations::default(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_to_utc_crosses_year() {
+        let mut photo = make_photo(datetime(2021, 1, 1, 1, 0, 0), Some(offset(9 * 3600)));
+        photo.normalize_to(TargetZone::Utc);
+        assert_eq!(photo.datetime, datetime(2020, 12, 31, 16, 0, 0));
+        assert_eq!(photo.offset, Some(offset(0)));
+    }
+
+    #[test]
+    fn test_normalize_to_fixed_offset() {
+        let mut photo = make_photo(datetime(2020, 6, 1, 12, 0, 0), Some(offset(-4 * 3600)));
+        photo.normalize_to(TargetZone::Fixed(offset(2 * 3600)));
+        assert_eq!(photo.datetime, datetime(2020, 6, 1, 18, 0, 0));
+        assert_eq!(photo.offset, Some(offset(2 * 3600)));
+    }
+
+    #[test]
+    fn test_normalize_without_offset_is_unchanged() {
+        let mut photo = make_photo(datetime(2020, 6, 1, 12, 0, 0), None);
+        photo.normalize_to(TargetZone::Utc);
+        assert_eq!(photo.datetime, datetime(2020, 6, 1, 12, 0, 0));
+        assert_eq!(photo.offset, None);
+    }
+
+    fn rational_field(tag: exif::Tag, values: &[(u32, u32)]) -> exif::Field {
+        exif::Field {
+            tag,
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Rational(
+                values
+                    .iter()
+                    .map(|&(num, denom)| exif::Rational { num, denom })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_extract_gps_time_from_field() {
+        let field = rational_field(exif::Tag::GPSTimeStamp, &[(13, 1), (5, 1), (4250, 100)]);
+        assert_eq!(
+            extract_gps_time_from_field(&field),
+            NaiveTime::from_hms_milli_opt(13, 5, 42, 500)
+        );
+    }
+
+    #[test]
+    fn test_extract_gps_time_from_field_invalid() {
+        let zero_denom = rational_field(exif::Tag::GPSTimeStamp, &[(13, 1), (5, 0), (42, 1)]);
+        assert!(extract_gps_time_from_field(&zero_denom).is_none());
+
+        let short = rational_field(exif::Tag::GPSTimeStamp, &[(13, 1), (5, 1)]);
+        assert!(extract_gps_time_from_field(&short).is_none());
+
+        let out_of_range = rational_field(exif::Tag::GPSTimeStamp, &[(25, 1), (0, 1), (0, 1)]);
+        assert!(extract_gps_time_from_field(&out_of_range).is_none());
+    }
+
+    #[test]
+    fn test_date_source_display_matches_cli_names() {
+        assert_eq!(DateSource::Xmp.to_string(), "xmp");
+        assert_eq!(DateSource::Original.to_string(), "original");
+        assert_eq!(DateSource::Takeout.to_string(), "takeout");
+        assert_eq!(DateSource::Filename.to_string(), "filename");
+        assert_eq!(DateSource::Dir.to_string(), "dir");
+        assert_eq!(DateSource::Mtime.to_string(), "mtime");
+        assert_eq!(DateSource::Btime.to_string(), "btime");
+        assert_eq!(DateSource::Modify.to_string(), "modify");
+        assert_eq!(DateSource::Digitized.to_string(), "digitized");
+        assert_eq!(DateSource::Gps.to_string(), "gps");
+        assert_eq!(DateSource::CreationDate.to_string(), "creationdate");
+        assert_eq!(DateSource::Mvhd.to_string(), "mvhd");
+    }
+
+    #[test]
+    fn test_scan_options_with_sources() {
+        let options = ScanOptions::with_sources(&[]);
+        assert_eq!(options.sources, DEFAULT_SOURCES.to_vec());
+
+        let options = ScanOptions::with_sources(&[
+            DateSource::Original,
+            DateSource::Gps,
+            DateSource::Original,
+        ]);
+        assert_eq!(options.sources, vec![DateSource::Original, DateSource::Gps]);
+    }
+
+    #[test]
+    fn test_scan_options_prefer() {
+        let mut options = ScanOptions::with_sources(&[DateSource::Original, DateSource::Gps]);
+        options.prefer(DateSource::Gps);
+        assert_eq!(options.sources, vec![DateSource::Gps, DateSource::Original]);
+
+        options.prefer(DateSource::Modify);
+        assert_eq!(
+            options.sources,
+            vec![DateSource::Modify, DateSource::Gps, DateSource::Original]
+        );
+    }
+
+    fn found(abs_path: &Path, format: FileFormat) -> FoundFile {
+        FoundFile {
+            rel_path: PathBuf::from(abs_path.file_name().unwrap()),
+            abs_path: abs_path.to_path_buf(),
+            format,
+            sidecars: Arc::new(SidecarNames::read(abs_path.parent().unwrap()).unwrap()),
+        }
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_read_capture_date_heic() {
+        let capture = read_capture_date(
+            &found(&fixture("iphone.heic"), FileFormat::Heif),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2022, 8, 14, 17, 45, 3));
+        assert_eq!(capture.offset, Some(offset(2 * 3600)));
+        assert_eq!(capture.source, DateSource::Original);
+    }
+
+    #[test]
+    fn test_read_capture_date_jpeg() {
+        let capture = read_capture_date(
+            &found(&fixture("sample_exif.jpg"), FileFormat::Jpeg),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2025, 6, 7, 11, 50, 11));
+        assert_eq!(capture.offset, None);
+    }
+
+    #[test]
+    fn test_read_capture_date_embedded_xmp_wins_over_exif() {
+        // The XMP segment of this file comes before the EXIF one
+        let path = fixture("scan_with_xmp.jpg");
+        let capture = read_capture_date(&found(&path, FileFormat::Jpeg), &ScanOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.datetime, datetime(1994, 7, 2, 0, 0, 0));
+        assert_eq!(capture.source, DateSource::Xmp);
+
+        let capture = read_capture_date(
+            &found(&path, FileFormat::Jpeg),
+            &ScanOptions::with_sources(&[DateSource::Digitized, DateSource::Xmp]),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.source, DateSource::Digitized);
+
+        let capture = read_capture_date(
+            &found(&path, FileFormat::Jpeg),
+            &ScanOptions::with_sources(&[DateSource::Xmp, DateSource::Digitized]),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.source, DateSource::Xmp);
+    }
+
+    #[test]
+    fn test_read_capture_date_sidecar_wins_over_embedded_xmp() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("scan.jpg");
+        std::fs::copy(fixture("scan_with_xmp.jpg"), &path).unwrap();
+        std::fs::write(
+            temp.path().join("scan.xmp"),
+            r#"<rdf:Description xmp:CreateDate="1993-12-25T09:15:00+01:00"/>"#,
+        )
+        .unwrap();
+
+        let capture = read_capture_date(&found(&path, FileFormat::Jpeg), &ScanOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.datetime, datetime(1993, 12, 25, 9, 15, 0));
+        assert_eq!(capture.offset, FixedOffset::east_opt(3600));
+    }
 
     #[test]
-    fn test_parse_exif_datetime_full() {
-        let date = parse_exif_datetime("2023:12:25 14:30:45").unwrap();
-        assert_eq!(date, NaiveDate::from_ymd_opt(2023, 12, 25).unwrap());
+    fn test_read_capture_date_filename_without_time_has_day_precision() {
+        let temp = tempfile::tempdir().unwrap();
+        let options = ScanOptions::with_sources(&[DateSource::Filename]);
+        for (name, precision) in [
+            ("IMG-20190715-WA0001.jpg", Precision::Day),
+            ("IMG_20190715_000000.jpg", Precision::Time),
+        ] {
+            let path = temp.path().join(name);
+            std::fs::write(&path, b"no metadata").unwrap();
+            let capture = read_capture_date(&found(&path, FileFormat::Jpeg), &options)
+                .unwrap()
+                .unwrap();
+            assert_eq!(capture.datetime, datetime(2019, 7, 15, 0, 0, 0), "{name}");
+            assert_eq!(capture.precision, precision, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_read_capture_date_takeout_sidecar() {
+        let temp = tempfile::tempdir().unwrap();
+        // Takeout strips EXIF from many files
+        let path = temp.path().join("IMG_1234(1).jpg");
+        std::fs::write(&path, b"\xFF\xD8\xFF\xD9").unwrap();
+        std::fs::write(
+            temp.path().join("IMG_1234.jpg(1).json"),
+            r#"{"photoTakenTime": {"timestamp": "1554112356"}}"#,
+        )
+        .unwrap();
+
+        let capture = read_capture_date(&found(&path, FileFormat::Jpeg), &ScanOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.datetime, datetime(2019, 4, 1, 9, 52, 36));
+        assert_eq!(capture.offset, FixedOffset::east_opt(0));
+        assert_eq!(capture.source, DateSource::Takeout);
     }
 
     #[test]
-    fn test_parse_exif_datetime_date_only() {
-        let date = parse_exif_datetime("2020:01:15").unwrap();
-        assert_eq!(date, NaiveDate::from_ymd_opt(2020, 1, 15).unwrap());
+    fn test_read_capture_date_mtime_fallback() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("print.tif");
+        std::fs::write(&path, b"no metadata").unwrap();
+        let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let result = read_capture_date(&found(&path, FileFormat::Tiff), &ScanOptions::default());
+        assert!(matches!(result, Err(PtimeError::Exif(_))));
+
+        let mut options = ScanOptions::default();
+        options.sources.push(DateSource::Mtime);
+        let capture = read_capture_date(&found(&path, FileFormat::Tiff), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.source, DateSource::Mtime);
+        let offset = capture.offset.unwrap();
+        assert_eq!(capture.datetime - offset, datetime(2001, 9, 9, 1, 46, 40));
+        assert!(capture.source.is_low_confidence());
+        assert!(!DateSource::Filename.is_low_confidence());
     }
 
     #[test]
-    fn test_parse_exif_datetime_with_hyphen() {
-        let date = parse_exif_datetime("2020-01-15 10:11:12").unwrap();
-        assert_eq!(date, NaiveDate::from_ymd_opt(2020, 1, 15).unwrap());
+    fn test_read_capture_date_sidecar_for_raw_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nikon.nef");
+        std::fs::copy(fixture("nikon.nef"), &path).unwrap();
+        std::fs::write(
+            temp.path().join("nikon.nef.xmp"),
+            "<exif:DateTimeOriginal>2018-05-21T07:15:00</exif:DateTimeOriginal>",
+        )
+        .unwrap();
+
+        let capture = read_capture_date(&found(&path, FileFormat::Nef), &ScanOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.datetime, datetime(2018, 5, 21, 7, 15, 0));
+        assert_eq!(capture.source, DateSource::Xmp);
     }
 
     #[test]
-    fn test_parse_exif_datetime_invalid() {
-        assert!(parse_exif_datetime("not a date").is_none());
-        assert!(parse_exif_datetime("2023:13:25 10:00:00").is_none()); // invalid month
-        assert!(parse_exif_datetime("").is_none());
+    fn test_read_capture_date_raw_variants() {
+        for (name, format) in [
+            ("nikon.nef", FileFormat::Nef),
+            ("panasonic.rw2", FileFormat::Rw2),
+            ("olympus.orf", FileFormat::Orf),
+        ] {
+            let capture =
+                read_capture_date(&found(&fixture(name), format), &ScanOptions::default())
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(capture.datetime, datetime(2018, 5, 20, 7, 15, 0), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_read_capture_date_png_and_webp() {
+        let capture = read_capture_date(
+            &found(&fixture("export.png"), FileFormat::Png),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2017, 11, 3, 16, 20, 0));
+
+        let capture = read_capture_date(
+            &found(&fixture("export.webp"), FileFormat::Webp),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2016, 4, 22, 9, 10, 11));
+    }
+
+    #[test]
+    fn test_read_capture_date_png_without_exif_has_no_date() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("plain.png");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\0IEND\0\0\0\0").unwrap();
+        let capture =
+            read_capture_date(&found(&path, FileFormat::Png), &ScanOptions::default()).unwrap();
+        assert_eq!(capture, None);
+
+        // A truncated EXIF chunk is damaged metadata, not missing metadata
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\x10eXIfMM").unwrap();
+        let err =
+            read_capture_date(&found(&path, FileFormat::Png), &ScanOptions::default()).unwrap_err();
+        assert!(matches!(err, PtimeError::Exif(_)));
+    }
+
+    #[test]
+    fn test_read_capture_date_mov_prefers_apple_creation_date() {
+        let capture = read_capture_date(
+            &found(&fixture("iphone_clip.mov"), FileFormat::Mov),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2019, 12, 24, 18, 30, 0));
+        assert_eq!(capture.offset, FixedOffset::east_opt(3600));
+        assert_eq!(capture.source, DateSource::CreationDate);
+
+        let capture = read_capture_date(
+            &found(&fixture("iphone_clip.mov"), FileFormat::Mov),
+            &ScanOptions::with_sources(&[DateSource::Mvhd]),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2019, 12, 24, 17, 30, 5));
+        assert_eq!(capture.offset, FixedOffset::east_opt(0));
+    }
+
+    #[test]
+    fn test_read_capture_date_mp4_falls_back_to_mvhd() {
+        let capture = read_capture_date(
+            &found(&fixture("camera_clip.mp4"), FileFormat::Mp4),
+            &ScanOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(capture.datetime, datetime(2015, 8, 2, 14, 0, 0));
+        assert_eq!(capture.source, DateSource::Mvhd);
+    }
+
+    #[test]
+    fn test_read_capture_date_exif_sources_ignore_videos() {
+        let result = read_capture_date(
+            &found(&fixture("camera_clip.mp4"), FileFormat::Mp4),
+            &ScanOptions::with_sources(&[DateSource::Original, DateSource::Gps]),
+        );
+        assert_eq!(result.unwrap(), None);
+
+        let result = read_capture_date(
+            &found(&fixture("sample_exif.jpg"), FileFormat::Jpeg),
+            &ScanOptions::with_sources(&[DateSource::Mvhd]),
+        );
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_capture_date_video_without_moov_is_video_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("broken.mp4");
+        std::fs::write(&path, b"\0\0\0\x10ftypisom\0\0\0\0").unwrap();
+        let err =
+            read_capture_date(&found(&path, FileFormat::Mp4), &ScanOptions::default()).unwrap_err();
+        assert!(matches!(err, PtimeError::Video(_)));
     }
 
     #[test]
     fn test_collect_photos_empty_directory() {
         use tempfile::tempdir;
         let temp = tempdir().unwrap();
-        let result = collect_photos(temp.path()).unwrap();
+        let result = collect_photos(temp.path(), &ScanOptions::default()).unwrap();
         assert!(result.is_empty());
     }
 
-    // Note: Testing with real EXIF data requires actual JPEG fixtures.
-    // For now, we test the parsing logic and empty directory handling.
-    // Integration tests with fixtures will be added in Prompt 7.
+    #[test]
+    fn test_collect_photos_same_order_for_any_job_count() {
+        let temp = tempfile::tempdir().unwrap();
+        for i in 0..40 {
+            let dir = temp.path().join(format!("roll_{}", i % 3));
+            std::fs::create_dir_all(&dir).unwrap();
+            let name = format!("IMG_2019{:02}{:02}_120000.jpg", i % 12 + 1, i % 28 + 1);
+            std::fs::write(dir.join(name), b"no exif").unwrap();
+            std::fs::write(dir.join(format!("junk_{i}.jpg")), b"no date").unwrap();
+        }
+
+        let sequential = ScanOptions {
+            jobs: NonZeroUsize::MIN,
+            ..ScanOptions::default()
+        };
+        let expected = collect_photos(temp.path(), &sequential).unwrap();
+        assert_eq!(expected.len(), 40);
+        for jobs in [2, 7, 64] {
+            let options = ScanOptions {
+                jobs: NonZeroUsize::new(jobs).unwrap(),
+                ..ScanOptions::default()
+            };
+            assert_eq!(collect_photos(temp.path(), &options).unwrap(), expected);
+        }
+    }
 }
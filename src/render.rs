@@ -1,24 +1,99 @@
+use crate::analysis::{Bucket, Granularity, Summary, WeekHours, YearDays};
+use crate::metadata::{DateSource, PhotoMeta, Precision};
+use chrono::{Datelike, NaiveDate, Weekday};
 use std::collections::BTreeMap;
+use std::fmt::Display;
 
 const BLOCK_CHAR: char = '\u{2588}'; // Unicode full block
 
-pub fn render_histogram(year_counts: &BTreeMap<i32, usize>, width: usize) -> Vec<String> {
-    if year_counts.is_empty() {
+// Calendar and punchcard cells: no photos, then four levels of activity
+const EMPTY_DAY: char = '\u{00B7}';
+const SHADES: [char; 4] = ['\u{2591}', '\u{2592}', '\u{2593}', '\u{2588}'];
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+// Column `stats` values start at
+const STATS_LABEL_WIDTH: usize = 26;
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+// Fill characters for stacked bars, assigned to sources in legend order
+const STACK_CHARS: [char; 8] = [
+    '\u{2588}', '\u{2593}', '\u{2592}', '\u{2591}', '\u{259A}', '\u{259E}', '\u{2599}', '\u{259B}',
+];
+
+/// Fields printed for a single photo by `oldest`/`latest`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhotoFormat {
+    /// Full timestamp (with offset when known) instead of the date only
+    pub timestamp: bool,
+    /// Append the metadata source the date came from
+    pub source: bool,
+}
+
+pub fn render_photo(photo: &PhotoMeta, format: PhotoFormat) -> String {
+    let mut line = if photo.approximate {
+        format!(
+            "{} {} (approx.)",
+            photo.rel_path.display(),
+            render_date(photo)
+        )
+    } else if photo.precision < Precision::Time || !format.timestamp {
+        format!("{} {}", photo.rel_path.display(), render_date(photo))
+    } else {
+        let offset = photo.offset.map(|o| o.to_string()).unwrap_or_default();
+        format!(
+            "{} {}{}",
+            photo.rel_path.display(),
+            photo.datetime.format(TIMESTAMP_FORMAT),
+            offset
+        )
+    };
+    if format.source && photo.normalized.is_empty() {
+        line.push_str(&format!(" [{}]", photo.source));
+    } else if format.source {
+        line.push_str(&format!(
+            " [{}, normalized: {}]",
+            photo.source, photo.normalized
+        ));
+    }
+    if photo.source.is_low_confidence() {
+        line.push_str(" (low confidence)");
+    }
+    line
+}
+
+/// Renders the known part of the capture date: `1987`, `1987-07` or a full day.
+fn render_date(photo: &PhotoMeta) -> String {
+    match photo.precision {
+        Precision::Year => photo.datetime.format("%Y").to_string(),
+        Precision::Month => photo.datetime.format("%Y-%m").to_string(),
+        Precision::Day | Precision::Time => photo.date().to_string(),
+    }
+}
+
+/// Renders one `LABEL BAR COUNT` line per bucket, in key order.
+pub fn render_histogram<K: Display>(counts: &BTreeMap<K, usize>, width: usize) -> Vec<String> {
+    if counts.is_empty() {
         return vec![];
     }
 
-    let max_count = *year_counts.values().max().unwrap_or(&0);
+    let max_count = *counts.values().max().unwrap_or(&0);
     if max_count == 0 {
         // All zeros, just format with no bars
-        return year_counts
+        return counts
             .iter()
-            .map(|(year, count)| format!("{}  {}", year, count))
+            .map(|(label, count)| format!("{}  {}", label, count))
             .collect();
     }
 
-    year_counts
+    counts
         .iter()
-        .map(|(year, &count)| {
+        .map(|(label, &count)| {
             let bar = if count == 0 {
                 String::new()
             } else {
@@ -27,18 +102,430 @@ pub fn render_histogram(year_counts: &BTreeMap<i32, usize>, width: usize) -> Vec
                 let bar_width = scaled.max(1);
                 BLOCK_CHAR.to_string().repeat(bar_width)
             };
-            format!("{} {} {}", year, bar, count)
+            format!("{} {} {}", label, bar, count)
         })
         .collect()
 }
 
+/// Renders per-bucket bars stacked by date source, followed by a legend
+/// line mapping each fill character to its source.
+pub fn render_source_histogram<K: Display>(
+    bucket_sources: &BTreeMap<K, BTreeMap<DateSource, usize>>,
+    width: usize,
+) -> Vec<String> {
+    if bucket_sources.is_empty() {
+        return vec![];
+    }
+
+    let mut present: Vec<DateSource> = bucket_sources
+        .values()
+        .flat_map(|sources| sources.keys().copied())
+        .collect();
+    present.sort();
+    present.dedup();
+    let fill_for = |source: &DateSource| {
+        let index = present.iter().position(|s| s == source).unwrap_or(0);
+        STACK_CHARS[index % STACK_CHARS.len()]
+    };
+
+    let max_count = bucket_sources
+        .values()
+        .map(|sources| sources.values().sum::<usize>())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines: Vec<String> = bucket_sources
+        .iter()
+        .map(|(label, sources)| {
+            let count: usize = sources.values().sum();
+            if count == 0 {
+                return format!("{}  {}", label, count);
+            }
+
+            // Scale cumulative counts so segment boundaries round consistently
+            let scale = width as f64 / max_count as f64;
+            let total_width = ((count as f64 * scale).round() as usize).max(1);
+            let mut bar = String::new();
+            let mut cumulative = 0;
+            let mut drawn = 0;
+            for (source, &source_count) in sources {
+                cumulative += source_count;
+                let end = ((cumulative as f64 * scale).round() as usize).min(total_width);
+                let end = if cumulative == count {
+                    total_width
+                } else {
+                    end
+                };
+                bar.extend(std::iter::repeat_n(
+                    fill_for(source),
+                    end.saturating_sub(drawn),
+                ));
+                drawn = drawn.max(end);
+            }
+            format!("{} {} {}", label, bar, count)
+        })
+        .collect();
+
+    if !present.is_empty() {
+        let legend: Vec<String> = present
+            .iter()
+            .map(|source| format!("{} {}", fill_for(source), source))
+            .collect();
+        lines.push(legend.join("  "));
+    }
+    lines
+}
+
+/// Note for photos left out of a histogram because their date is coarser
+/// than its unit, e.g. `(3 photos not dated to the month)`.
+pub fn render_unplaced(unplaced: usize, granularity: Granularity) -> Option<String> {
+    let reason = match granularity.precision() {
+        Precision::Year => return None,
+        Precision::Month => "not dated to the month",
+        Precision::Day => "not dated to the day",
+        Precision::Time => "without a time of day",
+    };
+    match unplaced {
+        0 => None,
+        1 => Some(format!("(1 photo {reason})")),
+        n => Some(format!("({n} photos {reason})")),
+    }
+}
+
+/// Renders a year as a grid with one row per weekday and one column per
+/// week, Monday first, like a GitHub contribution graph. Each day is shaded
+/// by its photo count relative to the busiest day; a legend gives the
+/// counts behind each shade.
+pub fn render_calendar(year_days: &YearDays) -> Vec<String> {
+    let year = year_days.year;
+    let (Some(jan1), Some(dec31)) = (
+        NaiveDate::from_ymd_opt(year, 1, 1),
+        NaiveDate::from_ymd_opt(year, 12, 31),
+    ) else {
+        return vec![];
+    };
+    let lead = jan1.weekday().num_days_from_monday() as usize;
+    let column = |date: NaiveDate| (date.ordinal0() as usize + lead) / 7;
+    let columns = column(dec31) + 1;
+    let max_count = year_days.days.values().copied().max().unwrap_or(0);
+
+    let photos = year_days.photos();
+    let days = year_days.days.len();
+    let mut lines = vec![format!(
+        "{year}: {photos} {} on {days} {}",
+        if photos == 1 { "photo" } else { "photos" },
+        if days == 1 { "day" } else { "days" },
+    )];
+
+    // Month names above the week their first day falls in
+    let mut header = vec![' '; columns + 3];
+    let mut free = 0;
+    for month in 1..=12 {
+        let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            continue;
+        };
+        let at = column(first);
+        if at >= free {
+            let name = first.format("%b").to_string();
+            for (offset, c) in name.chars().enumerate() {
+                header[at + offset] = c;
+            }
+            free = at + name.chars().count() + 1;
+        }
+    }
+    let header: String = header.into_iter().collect();
+    lines.push(format!("    {}", header.trim_end()));
+
+    for weekday in WEEKDAYS {
+        let mut row = format!("{weekday} ");
+        for week in 0..columns {
+            let day = (week * 7 + weekday.num_days_from_monday() as usize).checked_sub(lead);
+            let date = day.and_then(|day| jan1.checked_add_days(chrono::Days::new(day as u64)));
+            row.push(match date.filter(|date| date.year() == year) {
+                None => ' ',
+                Some(date) => match year_days.days.get(&date) {
+                    None | Some(0) => EMPTY_DAY,
+                    Some(&count) => SHADES[shade(count, max_count)],
+                },
+            });
+        }
+        lines.push(row.trim_end().to_string());
+    }
+
+    lines.push(format!("    {}", render_shade_legend(max_count)));
+    lines.extend(render_unplaced(year_days.unplaced, Granularity::Day));
+    lines
+}
+
+/// Renders the seven weekdays against the 24 hours of the day, each cell
+/// shaded by its photo count relative to the busiest hour, with the total
+/// per weekday at the end of its row.
+pub fn render_punchcard(week_hours: &WeekHours) -> Vec<String> {
+    let max_count = week_hours.max();
+    let hours: Vec<String> = (0..24).map(|hour| format!("{hour:02}")).collect();
+    let mut lines = vec![format!("    {}", hours.join(" "))];
+
+    for (weekday, counts) in WEEKDAYS.iter().zip(&week_hours.counts) {
+        let cells: Vec<String> = counts
+            .iter()
+            .map(|&count| {
+                let fill = match count {
+                    0 => EMPTY_DAY,
+                    count => SHADES[shade(count, max_count)],
+                };
+                format!("{fill}{fill}")
+            })
+            .collect();
+        let total: usize = counts.iter().sum();
+        lines.push(format!("{weekday} {} {total}", cells.join(" ")));
+    }
+
+    lines.push(format!("    {}", render_shade_legend(max_count)));
+    lines.extend(render_unplaced(week_hours.unplaced, Granularity::Hour));
+    lines
+}
+
+/// Renders one `Label: value` line per statistic. Date lines are left out
+/// when no photo is dated, and everything when no file was scanned.
+pub fn render_stats(summary: &Summary) -> Vec<String> {
+    if summary.scanned == 0 {
+        return vec![];
+    }
+    let line = |label: &str, value: String| {
+        format!(
+            "{:<width$}{value}",
+            format!("{label}:"),
+            width = STATS_LABEL_WIDTH
+        )
+    };
+    let mut lines = vec![
+        line("Supported files scanned", summary.scanned.to_string()),
+        line("Files dated", summary.dated.to_string()),
+    ];
+    let skipped: usize = summary.skipped.values().sum();
+    if skipped > 0 {
+        lines.push(line("Files skipped", skipped.to_string()));
+        for (reason, count) in &summary.skipped {
+            lines.push(line(&format!("  {reason}"), count.to_string()));
+        }
+    }
+
+    let (Some(oldest), Some(latest)) = (&summary.oldest, &summary.latest) else {
+        return lines;
+    };
+    lines.push(line(
+        "Earliest",
+        render_photo(oldest, PhotoFormat::default()),
+    ));
+    lines.push(line("Latest", render_photo(latest, PhotoFormat::default())));
+    let days = (latest.date() - oldest.date()).num_days();
+    let mut span = format!("{days} {}", if days == 1 { "day" } else { "days" });
+    if days >= 365 {
+        span.push_str(&format!(" ({:.1} years)", days as f64 / 365.2425));
+    }
+    lines.push(line("Span", span));
+    if let Some(median) = summary.median() {
+        lines.push(line("Median", median.to_string()));
+    }
+    let percentiles: Vec<String> = summary
+        .percentiles
+        .iter()
+        .filter(|(percent, _)| *percent != 50)
+        .map(|(percent, date)| format!("p{percent} {date}"))
+        .collect();
+    if !percentiles.is_empty() {
+        lines.push(line("Percentiles", percentiles.join("  ")));
+    }
+
+    let busiest = |(bucket, count): &(Bucket, usize)| {
+        format!(
+            "{bucket} ({count} {})",
+            if *count == 1 { "photo" } else { "photos" }
+        )
+    };
+    for (label, peak) in [
+        ("Busiest year", &summary.busiest_year),
+        ("Busiest month", &summary.busiest_month),
+        ("Busiest day", &summary.busiest_day),
+    ] {
+        if let Some(peak) = peak {
+            lines.push(line(label, busiest(peak)));
+        }
+    }
+    if let Some(mean) = summary.photos_per_active_day() {
+        lines.push(line("Active days", summary.active_days.to_string()));
+        lines.push(line("Photos per active day", format!("{mean:.1}")));
+    }
+    lines
+}
+
+/// Shade index for a non-zero count: the quarter of `max_count` it falls in.
+fn shade(count: usize, max_count: usize) -> usize {
+    (count * SHADES.len())
+        .div_ceil(max_count)
+        .clamp(1, SHADES.len())
+        - 1
+}
+
+/// Counts behind each shade of [`shade`], e.g. `· 0  ░ 1-12  ▒ 13-25 ...`.
+/// Shades no count can have are left out.
+fn render_shade_legend(max_count: usize) -> String {
+    let mut legend = vec![format!("{EMPTY_DAY} 0")];
+    for (level, fill) in SHADES.iter().enumerate() {
+        // Counts c with shade(c) == level
+        let low = level * max_count / SHADES.len() + 1;
+        let high = (level + 1) * max_count / SHADES.len();
+        match high.cmp(&low) {
+            std::cmp::Ordering::Less => {}
+            std::cmp::Ordering::Equal => legend.push(format!("{fill} {low}")),
+            std::cmp::Ordering::Greater => legend.push(format!("{fill} {low}-{high}")),
+        }
+    }
+    legend.join("  ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::datetime::{Normalization, Normalizations};
+    use crate::metadata::SkipReason;
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+
+    fn make_photo(nanos: u32) -> PhotoMeta {
+        PhotoMeta {
+            rel_path: PathBuf::from("dir/a.jpg"),
+            datetime: NaiveDate::from_ymd_opt(2023, 12, 25)
+                .unwrap()
+                .and_hms_nano_opt(14, 30, 45, nanos)
+                .unwrap(),
+            offset: None,
+            source: DateSource::Original,
+            precision: Precision::Time,
+            approximate: false,
+            normalized: Normalizations::default(),
+        }
+    }
+
+    const WITH_TIME: PhotoFormat = PhotoFormat {
+        timestamp: true,
+        source: false,
+    };
+
+    #[test]
+    fn test_render_photo_date_only() {
+        assert_eq!(
+            render_photo(&make_photo(0), PhotoFormat::default()),
+            "dir/a.jpg 2023-12-25"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_with_source() {
+        let mut photo = make_photo(0);
+        photo.source = DateSource::Modify;
+        let format = PhotoFormat {
+            timestamp: false,
+            source: true,
+        };
+        assert_eq!(
+            render_photo(&photo, format),
+            "dir/a.jpg 2023-12-25 [modify]"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_low_confidence() {
+        let mut photo = make_photo(0);
+        photo.source = DateSource::Mtime;
+        let format = PhotoFormat {
+            timestamp: false,
+            source: true,
+        };
+        assert_eq!(
+            render_photo(&photo, format),
+            "dir/a.jpg 2023-12-25 [mtime] (low confidence)"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_partial_dates() {
+        let mut photo = make_photo(0);
+        photo.precision = Precision::Year;
+        assert_eq!(render_photo(&photo, WITH_TIME), "dir/a.jpg 2023");
+        photo.precision = Precision::Month;
+        assert_eq!(
+            render_photo(&photo, PhotoFormat::default()),
+            "dir/a.jpg 2023-12"
+        );
+        photo.precision = Precision::Day;
+        assert_eq!(render_photo(&photo, WITH_TIME), "dir/a.jpg 2023-12-25");
+    }
+
+    #[test]
+    fn test_render_photo_normalized_source() {
+        let mut photo = make_photo(0);
+        photo.normalized.insert(Normalization::Separator);
+        photo.normalized.insert(Normalization::Delimiter);
+        let format = PhotoFormat {
+            timestamp: false,
+            source: true,
+        };
+        assert_eq!(
+            render_photo(&photo, format),
+            "dir/a.jpg 2023-12-25 [original, normalized: separator+delimiter]"
+        );
+        assert_eq!(
+            render_photo(&photo, PhotoFormat::default()),
+            "dir/a.jpg 2023-12-25"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_approximate() {
+        let mut photo = make_photo(0);
+        photo.precision = Precision::Year;
+        photo.approximate = true;
+        photo.source = DateSource::Dir;
+        let format = PhotoFormat {
+            timestamp: true,
+            source: true,
+        };
+        assert_eq!(
+            render_photo(&photo, format),
+            "dir/a.jpg 2023 (approx.) [dir]"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_with_time() {
+        assert_eq!(
+            render_photo(&make_photo(0), WITH_TIME),
+            "dir/a.jpg 2023-12-25T14:30:45"
+        );
+        assert_eq!(
+            render_photo(&make_photo(260_000_000), WITH_TIME),
+            "dir/a.jpg 2023-12-25T14:30:45.260"
+        );
+    }
+
+    #[test]
+    fn test_render_photo_with_offset() {
+        let mut photo = make_photo(0);
+        photo.offset = chrono::FixedOffset::east_opt(9 * 3600);
+        assert_eq!(
+            render_photo(&photo, WITH_TIME),
+            "dir/a.jpg 2023-12-25T14:30:45+09:00"
+        );
+        assert_eq!(
+            render_photo(&photo, PhotoFormat::default()),
+            "dir/a.jpg 2023-12-25"
+        );
+    }
 
     #[test]
     fn test_render_histogram_empty() {
-        let hist = BTreeMap::new();
+        let hist: BTreeMap<i32, usize> = BTreeMap::new();
         let lines = render_histogram(&hist, 50);
         assert!(lines.is_empty());
     }
@@ -148,4 +635,206 @@ mod tests {
         // Should contain blocks
         assert!(line.contains(BLOCK_CHAR));
     }
+
+    #[test]
+    fn test_render_source_histogram_empty() {
+        let hist: BTreeMap<i32, BTreeMap<DateSource, usize>> = BTreeMap::new();
+        assert!(render_source_histogram(&hist, 50).is_empty());
+    }
+
+    #[test]
+    fn test_render_source_histogram_stacks_segments() {
+        let mut hist = BTreeMap::new();
+        hist.insert(
+            2020,
+            BTreeMap::from([(DateSource::Original, 6), (DateSource::Modify, 4)]),
+        );
+        hist.insert(2021, BTreeMap::new());
+        hist.insert(2022, BTreeMap::from([(DateSource::Modify, 5)]));
+
+        let lines = render_source_histogram(&hist, 10);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "2020 \u{2588}\u{2588}\u{2588}\u{2588}\u{2588}\u{2588}\u{2593}\u{2593}\u{2593}\u{2593} 10");
+        assert_eq!(lines[1], "2021  0");
+        assert_eq!(lines[2], "2022 \u{2593}\u{2593}\u{2593}\u{2593}\u{2593} 5");
+        assert_eq!(lines[3], "\u{2588} original  \u{2593} modify");
+    }
+
+    #[test]
+    fn test_render_source_histogram_small_count_visible() {
+        let mut hist = BTreeMap::new();
+        hist.insert(2020, BTreeMap::from([(DateSource::Original, 100)]));
+        hist.insert(2021, BTreeMap::from([(DateSource::Gps, 1)]));
+
+        let lines = render_source_histogram(&hist, 10);
+        assert_eq!(lines[1], "2021 \u{2593} 1");
+    }
+
+    #[test]
+    fn test_render_unplaced() {
+        assert_eq!(render_unplaced(0, Granularity::Month), None);
+        assert_eq!(
+            render_unplaced(1, Granularity::Week),
+            Some("(1 photo not dated to the day)".to_string())
+        );
+        assert_eq!(
+            render_unplaced(3, Granularity::Hour),
+            Some("(3 photos without a time of day)".to_string())
+        );
+    }
+
+    fn year_days(year: i32, days: &[((u32, u32), usize)]) -> YearDays {
+        YearDays {
+            year,
+            days: days
+                .iter()
+                .map(|&((month, day), count)| {
+                    (NaiveDate::from_ymd_opt(year, month, day).unwrap(), count)
+                })
+                .collect(),
+            unplaced: 0,
+        }
+    }
+
+    #[test]
+    fn test_render_calendar_places_days_by_week_and_weekday() {
+        // 2021-01-01 is a Friday, 2021-01-04 the following Monday
+        let lines = render_calendar(&year_days(2021, &[((1, 1), 1), ((1, 4), 4)]));
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "2021: 5 photos on 2 days");
+        assert!(lines[1].starts_with("    Jan  Feb "));
+        assert!(lines[2].starts_with("Mon  \u{2588}\u{00B7}"));
+        assert!(lines[6].starts_with("Fri \u{2591}\u{00B7}"));
+        // 53 weeks, the last one ending on Friday 2021-12-31
+        assert_eq!(lines[6].chars().count(), 4 + 53);
+        assert_eq!(lines[7].chars().count(), 4 + 52);
+        assert_eq!(
+            lines[9],
+            "    \u{00B7} 0  \u{2591} 1  \u{2592} 2  \u{2593} 3  \u{2588} 4"
+        );
+    }
+
+    #[test]
+    fn test_render_calendar_shades_by_quarter_of_busiest_day() {
+        let lines = render_calendar(&year_days(2024, &[((1, 1), 100), ((1, 2), 30)]));
+        // 2024-01-01 is a Monday
+        assert!(lines[2].starts_with("Mon \u{2588}"));
+        assert!(lines[3].starts_with("Tue \u{2592}"));
+        assert_eq!(
+            lines[9],
+            "    \u{00B7} 0  \u{2591} 1-25  \u{2592} 26-50  \u{2593} 51-75  \u{2588} 76-100"
+        );
+    }
+
+    #[test]
+    fn test_render_calendar_notes_photos_without_a_day() {
+        let mut days = year_days(1987, &[]);
+        days.unplaced = 2;
+        let lines = render_calendar(&days);
+        assert_eq!(lines[0], "1987: 0 photos on 0 days");
+        assert_eq!(lines[9], "    \u{00B7} 0");
+        assert_eq!(lines[10], "(2 photos not dated to the day)");
+    }
+
+    #[test]
+    fn test_render_punchcard() {
+        let mut week_hours = WeekHours::default();
+        week_hours.counts[0][9] = 8;
+        week_hours.counts[0][10] = 2;
+        week_hours.counts[6][3] = 1;
+        week_hours.unplaced = 4;
+
+        let lines = render_punchcard(&week_hours);
+        assert_eq!(lines.len(), 10);
+        assert!(lines[0].starts_with("    00 01 02 03"));
+        assert!(lines[0].ends_with(" 22 23"));
+        let monday: Vec<&str> = lines[1].split(' ').collect();
+        assert_eq!(monday.len(), 1 + 24 + 1);
+        assert_eq!(monday[0], "Mon");
+        assert_eq!(monday[1], "\u{00B7}\u{00B7}");
+        assert_eq!(monday[10], "\u{2588}\u{2588}");
+        assert_eq!(monday[11], "\u{2591}\u{2591}");
+        assert_eq!(monday[25], "10");
+        assert!(lines[7].starts_with(
+            "Sun \u{00B7}\u{00B7} \u{00B7}\u{00B7} \u{00B7}\u{00B7} \u{2591}\u{2591} "
+        ));
+        assert!(lines[7].ends_with(" 1"));
+        assert_eq!(
+            lines[8],
+            "    \u{00B7} 0  \u{2591} 1-2  \u{2592} 3-4  \u{2593} 5-6  \u{2588} 7-8"
+        );
+        assert_eq!(lines[9], "(4 photos without a time of day)");
+    }
+
+    #[test]
+    fn test_render_stats() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut oldest = make_photo(0);
+        oldest.rel_path = PathBuf::from("scans/old.jpg");
+        oldest.datetime = day(1987, 1, 1).and_hms_opt(0, 0, 0).unwrap();
+        oldest.precision = Precision::Year;
+        let summary = Summary {
+            scanned: 7,
+            dated: 4,
+            skipped: BTreeMap::from([(SkipReason::NoDate, 2), (SkipReason::Unreadable, 1)]),
+            oldest: Some(oldest),
+            latest: Some(make_photo(0)),
+            percentiles: vec![
+                (25, day(2023, 1, 2)),
+                (50, day(2023, 6, 30)),
+                (75, day(2023, 12, 25)),
+            ],
+            busiest_year: Some((Bucket::Year(2023), 3)),
+            busiest_month: Some((
+                Bucket::Month {
+                    year: 2023,
+                    month: 12,
+                },
+                1,
+            )),
+            busiest_day: Some((Bucket::Day(day(2023, 12, 25)), 1)),
+            active_days: 3,
+            day_photos: 3,
+        };
+
+        assert_eq!(
+            render_stats(&summary),
+            vec![
+                "Supported files scanned:  7",
+                "Files dated:              4",
+                "Files skipped:            3",
+                "  no date:                2",
+                "  unreadable metadata:    1",
+                "Earliest:                 scans/old.jpg 1987",
+                "Latest:                   dir/a.jpg 2023-12-25",
+                "Span:                     13507 days (37.0 years)",
+                "Median:                   2023-06-30",
+                "Percentiles:              p25 2023-01-02  p75 2023-12-25",
+                "Busiest year:             2023 (3 photos)",
+                "Busiest month:            2023-12 (1 photo)",
+                "Busiest day:              2023-12-25 (1 photo)",
+                "Active days:              3",
+                "Photos per active day:    1.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_stats_without_dated_photos() {
+        let summary = Summary {
+            scanned: 1,
+            skipped: BTreeMap::from([(SkipReason::NoDate, 1)]),
+            ..Summary::default()
+        };
+        assert_eq!(
+            render_stats(&summary),
+            vec![
+                "Supported files scanned:  1",
+                "Files dated:              0",
+                "Files skipped:            1",
+                "  no date:                1",
+            ]
+        );
+        assert!(render_stats(&Summary::default()).is_empty());
+    }
 }
@@ -1,14 +1,67 @@
 use crate::error::PtimeError;
+use crate::format::FileFormat;
+use std::collections::{HashSet, VecDeque};
+use std::ffi::OsString;
+use std::iter::Peekable;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use walkdir::WalkDir;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FoundFile {
     pub rel_path: PathBuf,
     pub abs_path: PathBuf,
+    pub format: FileFormat,
+    /// Sidecars next to the file, shared by all files of its directory
+    pub sidecars: Arc<SidecarNames>,
 }
 
-pub fn scan_candidates(root: &Path) -> Result<Vec<FoundFile>, PtimeError> {
+/// Names of the XMP and JSON files in one directory, so that looking for a
+/// file's sidecars needs no file system access.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SidecarNames(HashSet<OsString>);
+
+impl SidecarNames {
+    /// Lists the sidecars in `dir`, for files that do not come from a scan.
+    pub fn read(dir: &Path) -> std::io::Result<Self> {
+        let mut names = SidecarNames::default();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                names.insert(&entry.path());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Whether `path` is one of the listed sidecars.
+    pub fn contains(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| self.0.contains(name))
+    }
+
+    fn insert(&mut self, path: &Path) {
+        let is_sidecar = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("xmp") || ext.eq_ignore_ascii_case("json"));
+        if let (true, Some(name)) = (is_sidecar, path.file_name()) {
+            self.0.insert(name.to_os_string());
+        }
+    }
+}
+
+/// Supported files under a scan root, yielded one at a time as the
+/// directory tree is walked so that no list of the whole tree is built.
+pub struct Scan {
+    root: PathBuf,
+    canonical_root: PathBuf,
+    walker: Peekable<walkdir::IntoIter>,
+    /// Supported files of the directory being yielded
+    pending: VecDeque<Result<FoundFile, PtimeError>>,
+}
+
+/// Starts a scan of `root`, which must exist.
+pub fn scan(root: &Path) -> Result<Scan, PtimeError> {
     // Canonicalize the root to get absolute path
     let canonical_root = root
         .canonicalize()
@@ -16,52 +69,95 @@ pub fn scan_candidates(root: &Path) -> Result<Vec<FoundFile>, PtimeError> {
             path: root.to_path_buf(),
             source: e,
         })?;
-
-    let mut results = Vec::new();
-
-    for entry in WalkDir::new(&canonical_root)
+    // Files come before subdirectories, so each directory's files arrive
+    // together and their names double as its sidecar listing
+    let walker = WalkDir::new(&canonical_root)
         .follow_links(false)
+        .sort_by(|a, b| {
+            (a.file_type().is_dir(), a.file_name()).cmp(&(b.file_type().is_dir(), b.file_name()))
+        })
         .into_iter()
-    {
-        let entry = entry.map_err(|e| {
-            let path = e.path().unwrap_or(root).to_path_buf();
-            PtimeError::DirectoryReadError {
-                path,
-                source: e.into(),
-            }
-        })?;
+        .peekable();
+
+    Ok(Scan {
+        root: root.to_path_buf(),
+        canonical_root,
+        walker,
+        pending: VecDeque::new(),
+    })
+}
 
-        // Skip directories
-        if !entry.file_type().is_file() {
-            continue;
+impl Scan {
+    /// Takes `first` and the entries after it that share its directory.
+    fn directory_files(&mut self, first: walkdir::DirEntry) -> Vec<walkdir::DirEntry> {
+        let mut files = vec![first];
+        while let Some(Ok(next)) = self.walker.peek() {
+            if next.file_type().is_dir() || next.path().parent() != files[0].path().parent() {
+                break;
+            }
+            if let Some(Ok(next)) = self.walker.next() {
+                files.push(next);
+            }
         }
+        files.retain(|entry| entry.file_type().is_file());
+        files
+    }
+}
 
-        let abs_path = entry.path();
+impl Iterator for Scan {
+    type Item = Result<FoundFile, PtimeError>;
 
-        // Check if it's a JPEG file
-        if !is_jpeg_extension(abs_path) {
-            continue;
-        }
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(found) = self.pending.pop_front() {
+                return Some(found);
+            }
+            let entry = match self.walker.next()? {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(&self.root).to_path_buf();
+                    return Some(Err(PtimeError::DirectoryReadError {
+                        path,
+                        source: e.into(),
+                    }));
+                }
+            };
 
-        // Compute relative path
-        let rel_path = compute_relative_path(&canonical_root, abs_path)?;
+            // Skip directories
+            if !entry.file_type().is_file() {
+                continue;
+            }
 
-        results.push(FoundFile {
-            rel_path,
-            abs_path: abs_path.to_path_buf(),
-        });
-    }
+            let files = self.directory_files(entry);
+            let mut sidecars = SidecarNames::default();
+            for file in &files {
+                sidecars.insert(file.path());
+            }
+            let sidecars = Arc::new(sidecars);
 
-    Ok(results)
+            for file in files {
+                let abs_path = file.path();
+                // Check if it's a format we can read dates from
+                let Some(format) = FileFormat::from_path(abs_path) else {
+                    continue;
+                };
+                self.pending
+                    .push_back(compute_relative_path(&self.canonical_root, abs_path).map(
+                        |rel_path| FoundFile {
+                            rel_path,
+                            abs_path: abs_path.to_path_buf(),
+                            format,
+                            sidecars: Arc::clone(&sidecars),
+                        },
+                    ));
+            }
+        }
+    }
 }
 
-fn is_jpeg_extension(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_lower = ext.to_string_lossy().to_lowercase();
-        ext_lower == "jpg" || ext_lower == "jpeg"
-    } else {
-        false
-    }
+/// Collects a whole scan, stopping at the first error.
+pub fn scan_candidates(root: &Path) -> Result<Vec<FoundFile>, PtimeError> {
+    scan(root)?.collect()
 }
 
 fn compute_relative_path(root: &Path, abs_path: &Path) -> Result<PathBuf, PtimeError> {
@@ -96,7 +192,7 @@ mod tests {
         fs::write(temp_path.join("photo2.JPEG"), b"fake jpeg").unwrap();
         fs::write(temp_path.join("photo3.JPG"), b"fake jpeg").unwrap();
         fs::write(temp_path.join("document.txt"), b"not a jpeg").unwrap();
-        fs::write(temp_path.join("image.png"), b"not a jpeg").unwrap();
+        fs::write(temp_path.join("image.gif"), b"not a jpeg").unwrap();
 
         let result = scan_candidates(temp_path).unwrap();
         assert_eq!(result.len(), 3);
@@ -149,17 +245,93 @@ mod tests {
     }
 
     #[test]
-    fn test_is_jpeg_extension() {
-        assert!(is_jpeg_extension(Path::new("photo.jpg")));
-        assert!(is_jpeg_extension(Path::new("photo.jpeg")));
-        assert!(is_jpeg_extension(Path::new("photo.JPG")));
-        assert!(is_jpeg_extension(Path::new("photo.JPEG")));
-        assert!(is_jpeg_extension(Path::new("photo.JpG")));
-
-        assert!(!is_jpeg_extension(Path::new("photo.png")));
-        assert!(!is_jpeg_extension(Path::new("photo.gif")));
-        assert!(!is_jpeg_extension(Path::new("photo")));
-        assert!(!is_jpeg_extension(Path::new("photo.txt")));
+    fn test_scan_finds_heif_files() {
+        let temp = tempdir().unwrap();
+        let temp_path = temp.path();
+
+        fs::write(temp_path.join("IMG_0001.HEIC"), b"fake heic").unwrap();
+        fs::write(temp_path.join("IMG_0002.heif"), b"fake heif").unwrap();
+        fs::write(temp_path.join("IMG_0003.avif"), b"not supported").unwrap();
+
+        let result = scan_candidates(temp_path).unwrap();
+        let rel_paths: Vec<_> = result.iter().map(|f| f.rel_path.clone()).collect();
+        assert_eq!(rel_paths.len(), 2);
+        assert!(rel_paths.contains(&PathBuf::from("IMG_0001.HEIC")));
+        assert!(rel_paths.contains(&PathBuf::from("IMG_0002.heif")));
+    }
+
+    #[test]
+    fn test_scan_finds_raw_files() {
+        let temp = tempdir().unwrap();
+        let temp_path = temp.path();
+
+        fs::write(temp_path.join("DSC_0001.NEF"), b"fake raw").unwrap();
+        fs::write(temp_path.join("DSC_0001.JPG"), b"fake jpeg").unwrap();
+        fs::write(temp_path.join("P1000001.rw2"), b"fake raw").unwrap();
+        fs::write(temp_path.join("scan.tif"), b"fake tiff").unwrap();
+        fs::write(temp_path.join("DSC_0001.xmp"), b"sidecar").unwrap();
+
+        let mut result = scan_candidates(temp_path).unwrap();
+        result.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        let found: Vec<_> = result
+            .iter()
+            .map(|f| (f.rel_path.clone(), f.format))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (PathBuf::from("DSC_0001.JPG"), FileFormat::Jpeg),
+                (PathBuf::from("DSC_0001.NEF"), FileFormat::Nef),
+                (PathBuf::from("P1000001.rw2"), FileFormat::Rw2),
+                (PathBuf::from("scan.tif"), FileFormat::Tiff),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_lists_sidecars_per_directory() {
+        let temp = tempdir().unwrap();
+        fs::create_dir(temp.path().join("a")).unwrap();
+        fs::write(temp.path().join("a/IMG_1.jpg"), b"fake").unwrap();
+        fs::create_dir(temp.path().join("a/nested")).unwrap();
+        fs::write(temp.path().join("a/nested/IMG_2.jpg"), b"fake").unwrap();
+        fs::write(temp.path().join("a/nested/IMG_2.jpg.json"), b"{}").unwrap();
+        fs::write(temp.path().join("a/IMG_1.XMP"), b"sidecar").unwrap();
+        fs::write(temp.path().join("a/notes.txt"), b"text").unwrap();
+
+        let mut result = scan_candidates(temp.path()).unwrap();
+        result.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        assert_eq!(result.len(), 2);
+        let dir_a = temp.path().join("a");
+        assert!(result[0].sidecars.contains(&dir_a.join("IMG_1.XMP")));
+        assert!(!result[0].sidecars.contains(&dir_a.join("IMG_1.xmp")));
+        assert!(!result[0].sidecars.contains(&dir_a.join("notes.txt")));
+        assert!(result[1]
+            .sidecars
+            .contains(&dir_a.join("nested/IMG_2.jpg.json")));
+        assert!(!result[1].sidecars.contains(&dir_a.join("IMG_1.XMP")));
+        assert_eq!(*result[0].sidecars, SidecarNames::read(&dir_a).unwrap());
+    }
+
+    #[test]
+    fn test_scan_yields_files_lazily() {
+        let temp = tempdir().unwrap();
+        for i in 0..5 {
+            fs::write(temp.path().join(format!("{i}.jpg")), b"fake").unwrap();
+        }
+
+        let mut files = scan(temp.path()).unwrap();
+        assert!(files.next().unwrap().is_ok());
+        assert_eq!(files.count(), 4);
+    }
+
+    #[test]
+    fn test_scan_missing_root_fails_upfront() {
+        let temp = tempdir().unwrap();
+        assert!(matches!(
+            scan(&temp.path().join("missing")),
+            Err(PtimeError::CanonicalizationError { .. })
+        ));
     }
 
     #[test]
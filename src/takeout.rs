@@ -0,0 +1,169 @@
+use chrono::{DateTime, NaiveDateTime};
+use std::path::{Path, PathBuf};
+
+/// Takeout keeps sidecar file names, `.json` included, within 51 characters
+/// by truncating whatever precedes the extension.
+const MAX_SIDECAR_NAME: usize = 51;
+const JSON_EXT: &str = ".json";
+/// Suffix used by newer exports: `IMG_1234.jpg.supplemental-metadata.json`
+const SUPPLEMENTAL_SUFFIX: &str = ".supplemental-metadata";
+
+/// Sidecar files Google Takeout may have written for `path`, in lookup order.
+///
+/// `IMG_1234.jpg` is described by `IMG_1234.jpg.json`, long names are cut to
+/// fit (`a_very_long_name.j.json`), and the duplicate `IMG_1234(1).jpg` by
+/// `IMG_1234.jpg(1).json`, with the counter moved behind the extension.
+pub fn sidecar_paths(path: &Path) -> Vec<PathBuf> {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let (base, counter) = split_duplicate_counter(name);
+    let mut names = Vec::new();
+    if counter.is_some() {
+        // Older exports kept the counter in place
+        names.push(format!("{name}{JSON_EXT}"));
+    }
+    let counter = counter.unwrap_or("");
+
+    for suffix in ["", SUPPLEMENTAL_SUFFIX] {
+        let full = format!("{base}{suffix}");
+        let budget = MAX_SIDECAR_NAME - JSON_EXT.len() - counter.chars().count();
+        let truncated: String = full.chars().take(budget).collect();
+        names.push(format!("{full}{counter}{JSON_EXT}"));
+        names.push(format!("{truncated}{counter}{JSON_EXT}"));
+    }
+    // Some exports drop the media extension altogether
+    if let Some((stem, _)) = base.rsplit_once('.') {
+        names.push(format!("{stem}{counter}{JSON_EXT}"));
+    }
+
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(names.len());
+    for name in names {
+        let candidate = path.with_file_name(name);
+        if !paths.contains(&candidate) {
+            paths.push(candidate);
+        }
+    }
+    paths
+}
+
+/// Splits `IMG_1234(1).jpg` into `IMG_1234.jpg` and `(1)`.
+fn split_duplicate_counter(name: &str) -> (String, Option<&str>) {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, Some(ext)),
+        None => (name, None),
+    };
+    let counter = stem
+        .strip_suffix(')')
+        .and_then(|s| s.rsplit_once('('))
+        .filter(|(_, digits)| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()));
+    match counter {
+        Some((clean, _)) => {
+            let counter = &stem[clean.len()..];
+            let base = match ext {
+                Some(ext) => format!("{clean}.{ext}"),
+                None => clean.to_string(),
+            };
+            (base, Some(counter))
+        }
+        None => (name.to_string(), None),
+    }
+}
+
+/// Reads `photoTakenTime.timestamp` (seconds since the Unix epoch, UTC)
+/// from a Takeout metadata file. A zero timestamp means unset.
+pub fn read_photo_taken_time(json: &str) -> Option<NaiveDateTime> {
+    let key = json.find("\"photoTakenTime\"")?;
+    let object = &json[key..];
+    let object = &object[object.find('{')?..];
+    let object = &object[..object.find('}')?];
+
+    let value = &object[object.find("\"timestamp\"")? + "\"timestamp\"".len()..];
+    let value = value.trim_start().strip_prefix(':')?.trim_start();
+    let digits = value.strip_prefix('"').unwrap_or(value);
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let seconds = digits[..end].parse::<i64>().ok().filter(|s| *s > 0)?;
+
+    DateTime::from_timestamp(seconds, 0).map(|utc| utc.naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_read_photo_taken_time() {
+        let json = r#"{
+          "title": "IMG_1234.jpg",
+          "creationTime": { "timestamp": "1600000000", "formatted": "..." },
+          "photoTakenTime": {
+            "timestamp": "1554112356",
+            "formatted": "1 Apr 2019, 09:52:36 UTC"
+          }
+        }"#;
+        assert_eq!(
+            read_photo_taken_time(json),
+            NaiveDate::from_ymd_opt(2019, 4, 1)
+                .unwrap()
+                .and_hms_opt(9, 52, 36)
+        );
+    }
+
+    #[test]
+    fn test_read_photo_taken_time_missing_or_unset() {
+        assert_eq!(
+            read_photo_taken_time(r#"{"creationTime": {"timestamp": "1600000000"}}"#),
+            None
+        );
+        assert_eq!(
+            read_photo_taken_time(r#"{"photoTakenTime": {"timestamp": "0"}}"#),
+            None
+        );
+        assert_eq!(
+            read_photo_taken_time(r#"{"photoTakenTime": {"formatted": "x"}, "timestamp": "5"}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sidecar_paths_plain_name() {
+        let paths = sidecar_paths(Path::new("Takeout/IMG_1234.jpg"));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Takeout/IMG_1234.jpg.json"),
+                PathBuf::from("Takeout/IMG_1234.jpg.supplemental-metadata.json"),
+                PathBuf::from("Takeout/IMG_1234.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sidecar_paths_truncated_name() {
+        let name = "Screenshot_2019-04-01-09-52-36-123_com.app.jpg";
+        assert_eq!(name.len(), 46);
+        let paths = sidecar_paths(Path::new(name));
+        assert!(paths.contains(&PathBuf::from(format!("{name}.json"))));
+
+        let name = "Screenshot_2019-04-01-09-52-36-123_com.apps.jpg";
+        let paths = sidecar_paths(Path::new(name));
+        assert!(paths.contains(&PathBuf::from(
+            "Screenshot_2019-04-01-09-52-36-123_com.apps.jp.json"
+        )));
+    }
+
+    #[test]
+    fn test_sidecar_paths_duplicate_counter() {
+        let paths = sidecar_paths(Path::new("IMG_1234(1).jpg"));
+        assert_eq!(paths[0], PathBuf::from("IMG_1234(1).jpg.json"));
+        assert_eq!(paths[1], PathBuf::from("IMG_1234.jpg(1).json"));
+        assert!(paths.contains(&PathBuf::from("IMG_1234(1).json")));
+
+        // Parentheses that are not a counter are part of the name
+        let paths = sidecar_paths(Path::new("Party (Bob).jpg"));
+        assert_eq!(paths[0], PathBuf::from("Party (Bob).jpg.json"));
+    }
+}
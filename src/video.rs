@@ -0,0 +1,347 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Key of the Apple metadata item holding the local capture time
+const APPLE_CREATION_DATE_KEY: &[u8] = b"com.apple.quicktime.creationdate";
+/// Upper bound for the `moov` atom we are willing to buffer
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+/// `data` atom type indicator for UTF-8 text
+const WELL_KNOWN_UTF8: u32 = 1;
+
+/// Capture dates found in a QuickTime / ISO base media file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VideoDates {
+    /// `mvhd` creation time, in UTC
+    pub movie_header: Option<NaiveDateTime>,
+    /// `com.apple.quicktime.creationdate`, local time with its offset
+    pub apple_creation_date: Option<DateTime<FixedOffset>>,
+}
+
+/// Walks the top-level atoms up to `moov` and extracts its creation dates.
+pub fn read_video_dates<R: Read + Seek>(reader: &mut R) -> io::Result<VideoDates> {
+    while let Some((kind, size)) = read_atom_header(reader)? {
+        if &kind == b"moov" {
+            let size = match size {
+                Some(size) => size,
+                // Written last with size 0, as some recorders do
+                None => remaining_len(reader)?,
+            };
+            if size > MAX_MOOV_SIZE {
+                return Err(invalid_data("moov atom too large"));
+            }
+            let mut moov = vec![0u8; size as usize];
+            reader.read_exact(&mut moov)?;
+            return Ok(parse_moov(&moov));
+        }
+        match size {
+            Some(size) => {
+                let offset = i64::try_from(size).map_err(|_| invalid_data("atom too large"))?;
+                reader.seek(SeekFrom::Current(offset))?;
+            }
+            // Atom extends to the end of the file
+            None => break,
+        }
+    }
+
+    Err(invalid_data("no moov atom"))
+}
+
+/// Reads an atom header and returns its type and payload size. A size of
+/// `None` means the atom runs to the end of the file.
+fn read_atom_header<R: Read>(reader: &mut R) -> io::Result<Option<([u8; 4], Option<u64>)>> {
+    let mut header = [0u8; 8];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let kind = [header[4], header[5], header[6], header[7]];
+    let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+        0 => None,
+        1 => {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            Some(
+                u64::from_be_bytes(large)
+                    .checked_sub(16)
+                    .ok_or_else(|| invalid_data("invalid atom size"))?,
+            )
+        }
+        n => Some(
+            u64::from(n)
+                .checked_sub(8)
+                .ok_or_else(|| invalid_data("invalid atom size"))?,
+        ),
+    };
+    Ok(Some((kind, size)))
+}
+
+/// Number of bytes between the current position and the end of the file.
+fn remaining_len<R: Seek>(reader: &mut R) -> io::Result<u64> {
+    let position = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(end.saturating_sub(position))
+}
+
+/// Iterates over the child atoms of an in-memory atom payload.
+fn child_atoms(mut data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+        let kind = data.get(4..8)?;
+        let (header_len, total) = match size {
+            0 => (8, data.len()),
+            1 => (
+                16,
+                usize::try_from(u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)).ok()?,
+            ),
+            n => (8, n),
+        };
+        if total < header_len || total > data.len() {
+            return None;
+        }
+        let payload = &data[header_len..total];
+        data = &data[total..];
+        Some((kind, payload))
+    })
+}
+
+fn parse_moov(moov: &[u8]) -> VideoDates {
+    let mut dates = VideoDates::default();
+    for (kind, payload) in child_atoms(moov) {
+        match kind {
+            b"mvhd" => dates.movie_header = parse_mvhd(payload),
+            b"meta" => {
+                dates.apple_creation_date =
+                    dates.apple_creation_date.or_else(|| parse_meta(payload));
+            }
+            b"udta" => {
+                dates.apple_creation_date = dates.apple_creation_date.or_else(|| {
+                    child_atoms(payload)
+                        .filter(|(kind, _)| *kind == b"meta")
+                        .find_map(|(_, meta)| parse_meta(meta))
+                });
+            }
+            _ => {}
+        }
+    }
+    dates
+}
+
+fn parse_mvhd(payload: &[u8]) -> Option<NaiveDateTime> {
+    // Version 1 uses 64-bit times, version 0 32-bit, both after version/flags
+    let seconds = match payload.first()? {
+        1 => u64::from_be_bytes(payload.get(4..12)?.try_into().ok()?),
+        _ => u64::from(u32::from_be_bytes(payload.get(4..8)?.try_into().ok()?)),
+    };
+    // Zero means the field was never filled in
+    if seconds == 0 {
+        return None;
+    }
+    let epoch = NaiveDate::from_ymd_opt(1904, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    epoch.checked_add_signed(TimeDelta::try_seconds(i64::try_from(seconds).ok()?)?)
+}
+
+fn parse_meta(payload: &[u8]) -> Option<DateTime<FixedOffset>> {
+    // QuickTime `meta` atoms start with `hdlr`; ISO ones are full boxes
+    // with a version/flags word in front
+    let children = if payload.get(4..8) == Some(b"hdlr") {
+        payload
+    } else {
+        payload.get(4..)?
+    };
+
+    let mut keys = None;
+    let mut items = None;
+    for (kind, child) in child_atoms(children) {
+        match kind {
+            b"keys" => keys = Some(child),
+            b"ilst" => items = Some(child),
+            _ => {}
+        }
+    }
+
+    let index = find_key_index(keys?, APPLE_CREATION_DATE_KEY)?;
+    let value = find_item_text(items?, index)?;
+    DateTime::parse_from_str(value.trim(), "%Y-%m-%dT%H:%M:%S%z").ok()
+}
+
+/// Returns the 1-based index of `wanted` in a `keys` atom.
+fn find_key_index(keys: &[u8], wanted: &[u8]) -> Option<u32> {
+    // version/flags, entry count, then size-prefixed entries
+    let mut entries = keys.get(8..)?;
+    let mut index = 1;
+    while entries.len() >= 8 {
+        let size = u32::from_be_bytes(entries[0..4].try_into().ok()?) as usize;
+        if size < 8 || size > entries.len() {
+            return None;
+        }
+        // Skip the 4-byte namespace (usually `mdta`)
+        if &entries[8..size] == wanted {
+            return Some(index);
+        }
+        entries = &entries[size..];
+        index += 1;
+    }
+    None
+}
+
+/// Returns the UTF-8 value stored for the 1-based key `index` in `ilst`.
+fn find_item_text(items: &[u8], index: u32) -> Option<&str> {
+    let (_, item) = child_atoms(items).find(|(kind, _)| *kind == index.to_be_bytes())?;
+    let (_, data) = child_atoms(item).find(|(kind, _)| *kind == b"data")?;
+    // type indicator, locale, then the value
+    let type_indicator = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?);
+    if type_indicator != WELL_KNOWN_UTF8 {
+        return None;
+    }
+    std::str::from_utf8(data.get(8..)?).ok()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn atom(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut atom = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(payload);
+        atom
+    }
+
+    fn mvhd_v0(seconds: u32) -> Vec<u8> {
+        let mut payload = vec![0, 0, 0, 0];
+        payload.extend_from_slice(&seconds.to_be_bytes()); // creation
+        payload.extend_from_slice(&seconds.to_be_bytes()); // modification
+        payload.extend_from_slice(&[0; 88]);
+        atom(b"mvhd", &payload)
+    }
+
+    fn apple_meta(value: &str) -> Vec<u8> {
+        let mut keys = vec![0, 0, 0, 0, 0, 0, 0, 2];
+        for key in [&b"com.apple.quicktime.make"[..], APPLE_CREATION_DATE_KEY] {
+            keys.extend_from_slice(&((key.len() + 8) as u32).to_be_bytes());
+            keys.extend_from_slice(b"mdta");
+            keys.extend_from_slice(key);
+        }
+        let mut data = WELL_KNOWN_UTF8.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(value.as_bytes());
+        let mut make = WELL_KNOWN_UTF8.to_be_bytes().to_vec();
+        make.extend_from_slice(&[0, 0, 0, 0]);
+        make.extend_from_slice(b"Apple");
+
+        let mut ilst = atom(&1u32.to_be_bytes(), &atom(b"data", &make));
+        ilst.extend(atom(&2u32.to_be_bytes(), &atom(b"data", &data)));
+
+        let mut meta = atom(b"hdlr", &[0; 25]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &ilst));
+        atom(b"meta", &meta)
+    }
+
+    fn movie(moov_children: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend(atom(b"wide", &[]));
+        file.extend(atom(b"mdat", &[0; 32]));
+        file.extend(atom(b"moov", &moov_children.concat()));
+        file
+    }
+
+    #[test]
+    fn test_mvhd_creation_time() {
+        // 2020-01-01T00:00:00Z is 3_660_681_600 seconds after 1904-01-01
+        let file = movie(&[mvhd_v0(3_660_681_600)]);
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        assert_eq!(
+            dates.movie_header,
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(dates.apple_creation_date, None);
+    }
+
+    #[test]
+    fn test_mvhd_zero_is_unset() {
+        let file = movie(&[mvhd_v0(0)]);
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        assert_eq!(dates.movie_header, None);
+    }
+
+    #[test]
+    fn test_mvhd_version_1() {
+        let mut payload = vec![1, 0, 0, 0];
+        payload.extend_from_slice(&3_660_681_600u64.to_be_bytes());
+        payload.extend_from_slice(&[0; 100]);
+        let dates = parse_moov(&atom(b"mvhd", &payload));
+        assert_eq!(
+            dates.movie_header,
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_apple_creation_date() {
+        let file = movie(&[
+            mvhd_v0(3_660_681_600),
+            apple_meta("2019-12-31T23:30:00+0100"),
+        ]);
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        let apple = dates.apple_creation_date.unwrap();
+        assert_eq!(apple.to_rfc3339(), "2019-12-31T23:30:00+01:00");
+    }
+
+    #[test]
+    fn test_apple_creation_date_in_udta() {
+        let file = movie(&[atom(b"udta", &apple_meta("2021-06-01T08:00:00-0700"))]);
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        assert_eq!(
+            dates.apple_creation_date.unwrap().to_rfc3339(),
+            "2021-06-01T08:00:00-07:00"
+        );
+    }
+
+    #[test]
+    fn test_apple_creation_date_invalid_value() {
+        let file = movie(&[apple_meta("yesterday")]);
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        assert_eq!(dates.apple_creation_date, None);
+    }
+
+    #[test]
+    fn test_moov_running_to_end_of_file() {
+        let mut file = movie(&[mvhd_v0(3_660_681_600)]);
+        let expected = read_video_dates(&mut Cursor::new(&file)).unwrap();
+        // Size 0 in the last atom's header: it extends to the end of the file
+        let moov = file.len() - 8 - 108;
+        file[moov..moov + 4].copy_from_slice(&0u32.to_be_bytes());
+
+        let dates = read_video_dates(&mut Cursor::new(file)).unwrap();
+        assert!(dates.movie_header.is_some());
+        assert_eq!(dates, expected);
+    }
+
+    #[test]
+    fn test_missing_moov() {
+        let file = atom(b"ftyp", b"isom\0\0\0\0isom");
+        let err = read_video_dates(&mut Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_truncated_child_atoms_are_ignored() {
+        let mut moov = mvhd_v0(3_660_681_600);
+        moov.extend_from_slice(&[0, 0, 0, 99, b'm', b'e', b't', b'a']);
+        let dates = parse_moov(&moov);
+        assert!(dates.movie_header.is_some());
+        assert_eq!(dates.apple_creation_date, None);
+    }
+}
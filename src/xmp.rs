@@ -0,0 +1,281 @@
+use crate::metadata::{parse_utc_offset, Precision};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use std::path::{Path, PathBuf};
+
+/// XMP properties holding a capture date, in priority order. Editors such as
+/// Lightroom and darktable rewrite all of them when the capture time is fixed.
+const DATE_PROPERTIES: [&str; 3] = [
+    "exif:DateTimeOriginal",
+    "photoshop:DateCreated",
+    "xmp:CreateDate",
+];
+
+/// A date read from an XMP packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XmpDate {
+    pub datetime: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+    /// XMP allows `YYYY` and `YYYY-MM` for partially known dates
+    pub precision: Precision,
+}
+
+/// Returns the first capture date found in an XMP packet.
+pub fn read_xmp_date(packet: &str) -> Option<XmpDate> {
+    DATE_PROPERTIES
+        .iter()
+        .filter_map(|name| find_property(packet, name))
+        .find_map(parse_xmp_datetime)
+}
+
+/// Sidecar files that may describe `path`, in lookup order: `IMG_0001.jpg.xmp`
+/// (darktable) before `IMG_0001.xmp` (Lightroom). Both spellings of the
+/// extension are tried since sidecars are often written on case-insensitive
+/// file systems.
+pub fn sidecar_paths(path: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::with_capacity(4);
+    for ext in ["xmp", "XMP"] {
+        let mut appended = path.as_os_str().to_owned();
+        appended.push(".");
+        appended.push(ext);
+        paths.push(PathBuf::from(appended));
+    }
+    for ext in ["xmp", "XMP"] {
+        paths.push(path.with_extension(ext));
+    }
+    paths
+}
+
+/// Finds the value of a simple property written either as an attribute
+/// (`xmp:CreateDate="..."`) or as an element (`<xmp:CreateDate>...</...>`).
+fn find_property<'a>(packet: &'a str, name: &str) -> Option<&'a str> {
+    find_attribute(packet, name).or_else(|| find_element(packet, name))
+}
+
+fn find_attribute<'a>(packet: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = packet;
+    while let Some(pos) = rest.find(name) {
+        let preceded_by_space = rest[..pos]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !preceded_by_space {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let end = value[1..].find(quote)?;
+        return Some(&value[1..1 + end]);
+    }
+    None
+}
+
+fn find_element<'a>(packet: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut rest = packet;
+    while let Some(pos) = rest.find(&open) {
+        rest = &rest[pos + open.len()..];
+        // Skip longer names sharing the prefix, e.g. `<xmp:CreateDateX>`
+        match rest.chars().next() {
+            Some('>') => {}
+            Some(c) if c.is_whitespace() => {}
+            _ => continue,
+        }
+        let start = rest.find('>')? + 1;
+        if rest[..start].ends_with("/>") {
+            continue;
+        }
+        let end = rest[start..].find(&close)?;
+        return Some(rest[start..start + end].trim());
+    }
+    None
+}
+
+/// Parses an XMP date (ISO 8601 subset: `YYYY[-MM[-DD[Thh:mm[:ss[.s+]][TZD]]]]`).
+/// Some writers copy the EXIF `YYYY:MM:DD hh:mm:ss` form verbatim, which is
+/// accepted as well.
+fn parse_xmp_datetime(value: &str) -> Option<XmpDate> {
+    let value = value.trim();
+    if let Some(partial) = parse_partial_date(value) {
+        return Some(partial);
+    }
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y:%m:%d"));
+    let Ok(date) = date else {
+        return parse_exif_style(value);
+    };
+    let Some(time) = time else {
+        return Some(XmpDate {
+            datetime: date.and_time(NaiveTime::MIN),
+            offset: None,
+            precision: Precision::Day,
+        });
+    };
+
+    let (clock, offset) = split_zone(time)?;
+    let clock = NaiveTime::parse_from_str(clock, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(clock, "%H:%M"))
+        .ok()?;
+    Some(XmpDate {
+        datetime: date.and_time(clock),
+        offset,
+        precision: Precision::Time,
+    })
+}
+
+fn parse_exif_style(value: &str) -> Option<XmpDate> {
+    let (date, time) = value.split_once(' ')?;
+    let date = NaiveDate::parse_from_str(date, "%Y:%m:%d").ok()?;
+    let (clock, offset) = split_zone(time.trim())?;
+    let clock = NaiveTime::parse_from_str(clock, "%H:%M:%S%.f").ok()?;
+    Some(XmpDate {
+        datetime: date.and_time(clock),
+        offset,
+        precision: Precision::Time,
+    })
+}
+
+/// Parses the year-only `1987` and year-month `1987-07` forms.
+fn parse_partial_date(value: &str) -> Option<XmpDate> {
+    let (year, month) = match value.split_once('-') {
+        Some((year, month)) if month.len() == 2 => (year, Some(month)),
+        Some(_) => return None,
+        None => (value, None),
+    };
+    if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year = year.parse().ok()?;
+    let (month, precision) = match month {
+        Some(month) => (month.parse().ok()?, Precision::Month),
+        None => (1, Precision::Year),
+    };
+    Some(XmpDate {
+        datetime: NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN),
+        offset: None,
+        precision,
+    })
+}
+
+/// Splits a time of day from its trailing zone designator, if any.
+fn split_zone(time: &str) -> Option<(&str, Option<FixedOffset>)> {
+    if let Some(clock) = time.strip_suffix('Z') {
+        return Some((clock, FixedOffset::east_opt(0)));
+    }
+    match time.find(['+', '-']) {
+        Some(pos) => Some((&time[..pos], Some(parse_utc_offset(&time[pos..])?))),
+        None => Some((time, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_attribute_form() {
+        let packet = r#"<rdf:Description rdf:about=""
+            xmp:ModifyDate="2023-01-01T10:00:00"
+            xmp:CreateDate="2008-05-17T14:03:22+02:00"/>"#;
+        let date = read_xmp_date(packet).unwrap();
+        assert_eq!(date.datetime, datetime(2008, 5, 17, 14, 3, 22));
+        assert_eq!(date.offset, FixedOffset::east_opt(7200));
+    }
+
+    #[test]
+    fn test_element_form() {
+        let packet = "<rdf:Description>\n\
+            <photoshop:DateCreated>1999-12-31T23:59</photoshop:DateCreated>\n\
+            </rdf:Description>";
+        let date = read_xmp_date(packet).unwrap();
+        assert_eq!(date.datetime, datetime(1999, 12, 31, 23, 59, 0));
+        assert_eq!(date.offset, None);
+    }
+
+    #[test]
+    fn test_original_wins_over_create_date() {
+        let packet = r#"<rdf:Description
+            xmp:CreateDate="2010-01-01T00:00:00Z"
+            exif:DateTimeOriginal="2004-07-04T12:00:00.5Z"/>"#;
+        let date = read_xmp_date(packet).unwrap();
+        assert_eq!(
+            date.datetime,
+            datetime(2004, 7, 4, 12, 0, 0) + chrono::TimeDelta::milliseconds(500)
+        );
+        assert_eq!(date.offset, FixedOffset::east_opt(0));
+    }
+
+    #[test]
+    fn test_invalid_value_falls_through() {
+        let packet = r#"<rdf:Description
+            exif:DateTimeOriginal="unknown"
+            xmp:CreateDate="2001-02-03"/>"#;
+        let date = read_xmp_date(packet).unwrap();
+        assert_eq!(date.datetime, datetime(2001, 2, 3, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_similar_names_are_not_matched() {
+        let packet = r#"<rdf:Description
+            myxmp:CreateDate="2001-02-03"
+            xmp:CreateDateX="2001-02-03">
+            <xmp:CreateDateY>2001-02-03</xmp:CreateDateY>
+            </rdf:Description>"#;
+        assert_eq!(read_xmp_date(packet), None);
+    }
+
+    #[test]
+    fn test_parse_xmp_datetime_exif_style() {
+        let date = parse_xmp_datetime("2012:03:04 05:06:07").unwrap();
+        assert_eq!(date.datetime, datetime(2012, 3, 4, 5, 6, 7));
+        let date = parse_xmp_datetime("2012-03-04T05:06:07-05:00").unwrap();
+        assert_eq!(date.offset, FixedOffset::west_opt(5 * 3600));
+        assert_eq!(parse_xmp_datetime("2012-03-04T25:00"), None);
+    }
+
+    #[test]
+    fn test_parse_xmp_partial_dates() {
+        let date = parse_xmp_datetime("1987").unwrap();
+        assert_eq!(date.datetime, datetime(1987, 1, 1, 0, 0, 0));
+        assert_eq!(date.precision, Precision::Year);
+        let date = parse_xmp_datetime("1987-07").unwrap();
+        assert_eq!(date.datetime, datetime(1987, 7, 1, 0, 0, 0));
+        assert_eq!(date.precision, Precision::Month);
+        assert_eq!(parse_xmp_datetime("1987-13"), None);
+        assert_eq!(parse_xmp_datetime("87"), None);
+    }
+
+    #[test]
+    fn test_sidecar_paths() {
+        let paths = sidecar_paths(Path::new("photos/IMG_0001.jpg"));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("photos/IMG_0001.jpg.xmp"),
+                PathBuf::from("photos/IMG_0001.jpg.XMP"),
+                PathBuf::from("photos/IMG_0001.xmp"),
+                PathBuf::from("photos/IMG_0001.XMP"),
+            ]
+        );
+    }
+}
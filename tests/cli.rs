@@ -8,8 +8,7 @@ use tempfile::tempdir;
 fn test_oldest_no_photos() {
     let temp = tempdir().unwrap();
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("oldest")
         .arg(temp.path())
         .assert()
@@ -21,8 +20,7 @@ fn test_oldest_no_photos() {
 fn test_latest_no_photos() {
     let temp = tempdir().unwrap();
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("latest")
         .arg(temp.path())
         .assert()
@@ -34,8 +32,7 @@ fn test_latest_no_photos() {
 fn test_hist_no_photos() {
     let temp = tempdir().unwrap();
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("hist")
         .arg(temp.path())
         .assert()
@@ -47,8 +44,7 @@ fn test_hist_no_photos() {
 fn test_hist_invalid_width_zero() {
     let temp = tempdir().unwrap();
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("hist")
         .arg("--width")
         .arg("0")
@@ -61,8 +57,7 @@ fn test_hist_invalid_width_zero() {
 
 #[test]
 fn test_nonexistent_directory() {
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("oldest")
         .arg("/nonexistent/path/12345")
         .assert()
@@ -72,8 +67,7 @@ fn test_nonexistent_directory() {
 
 #[test]
 fn test_help_command() {
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("--help")
         .assert()
         .success()
@@ -82,8 +76,7 @@ fn test_help_command() {
 
 #[test]
 fn test_oldest_subcommand_help() {
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("oldest")
         .arg("--help")
         .assert()
@@ -96,8 +89,7 @@ fn test_hist_width_parameter() {
     let temp = tempdir().unwrap();
 
     // Just verify the command accepts width parameter
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("hist")
         .arg("--width")
         .arg("100")
@@ -118,8 +110,7 @@ fn test_scan_finds_only_jpegs() {
 
     // The command will run but find no valid EXIF photos
     // (since our fake JPEGs don't have EXIF data)
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("oldest")
         .arg(temp_path)
         .assert()
@@ -131,8 +122,7 @@ fn test_scan_finds_only_jpegs() {
 fn test_latest_with_exif_fixture() {
     let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("latest")
         .arg(&fixtures)
         .assert()
@@ -151,8 +141,7 @@ fn test_permission_denied_propagates_io_error() {
     fs::write(&photo_path, b"fake jpeg").unwrap();
     fs::set_permissions(&photo_path, Permissions::from_mode(0o000)).unwrap();
 
-    Command::cargo_bin("ptime")
-        .unwrap()
+    ptime()
         .arg("oldest")
         .arg(temp.path())
         .assert()
@@ -162,3 +151,866 @@ fn test_permission_denied_propagates_io_error() {
 
     fs::set_permissions(&photo_path, Permissions::from_mode(0o600)).unwrap();
 }
+
+#[test]
+fn test_latest_with_timestamp() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    ptime()
+        .arg("latest")
+        .arg("--timestamp")
+        .arg(&fixtures)
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2025-06-07T11:50:11\n");
+}
+
+fn copy_fixture(name: &str, dir: &Path) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    fs::copy(fixtures.join(name), dir.join(name)).unwrap();
+}
+
+/// `ptime` without the on-disk cache, so tests never read or write the
+/// user's `~/.cache/ptime`. Tests of the cache pass `--cache` instead.
+fn ptime() -> Command {
+    let mut cmd = Command::cargo_bin("ptime").unwrap();
+    cmd.arg("--no-cache");
+    cmd
+}
+
+/// Moves the modification time of `path` an hour back: files modified in
+/// the last seconds are never cached.
+fn backdate(path: &Path) {
+    let file = fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .unwrap();
+}
+
+#[test]
+fn test_oldest_keeps_recorded_offset() {
+    let temp = tempdir().unwrap();
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg("--timestamp")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2021-01-01T01:00:00+09:00\n");
+}
+
+#[test]
+fn test_tz_utc_moves_photo_to_previous_year() {
+    let temp = tempdir().unwrap();
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+
+    ptime()
+        .arg("hist")
+        .arg("--tz")
+        .arg("utc")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("2020 "));
+
+    ptime()
+        .arg("oldest")
+        .arg("--timestamp")
+        .arg("--tz")
+        .arg("utc")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2020-12-31T16:00:00+00:00\n");
+}
+
+#[test]
+fn test_tz_accepts_negative_offset_as_separate_value() {
+    let temp = tempdir().unwrap();
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+
+    ptime()
+        .args(["--tz", "-05:00", "oldest", "--timestamp"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2020-12-31T11:00:00-05:00\n");
+
+    ptime()
+        .args(["oldest", "--timestamp", "--tz", "-0530"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2020-12-31T10:30:00-05:30\n");
+}
+
+#[test]
+fn test_gps_overrides_unset_camera_clock() {
+    let temp = tempdir().unwrap();
+    copy_fixture("gps_unset_clock.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("gps_unset_clock.jpg 2000-01-01\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--timestamp")
+        .arg("--gps")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("gps_unset_clock.jpg 2019-07-15T08:30:15+00:00\n");
+}
+
+#[test]
+fn test_date_source_can_ignore_digitized() {
+    let temp = tempdir().unwrap();
+    copy_fixture("scanned_negative.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("scanned_negative.jpg 2004-03-09\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--date-source")
+        .arg("original,modify")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_date_source_gps_only() {
+    let temp = tempdir().unwrap();
+    copy_fixture("gps_unset_clock.jpg", temp.path());
+    copy_fixture("scanned_negative.jpg", temp.path());
+
+    ptime()
+        .arg("latest")
+        .arg("--date-source")
+        .arg("gps")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("gps_unset_clock.jpg 2019-07-15\n");
+}
+
+#[test]
+fn test_show_source() {
+    let temp = tempdir().unwrap();
+    copy_fixture("scanned_negative.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("scanned_negative.jpg 2004-03-09 [digitized]\n");
+}
+
+#[test]
+fn test_malformed_exif_date_is_repaired_unless_strict() {
+    let temp = tempdir().unwrap();
+    copy_fixture("slash_date.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg("--timestamp")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("slash_date.jpg 2019-07-15T10:11:12 [original, normalized: separator]\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--date-parsing")
+        .arg("strict")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_output_does_not_depend_on_job_count() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let run = |args: &[&str], jobs: &str| {
+        let output = ptime()
+            .args(args)
+            .arg("--jobs")
+            .arg(jobs)
+            .arg(&fixtures)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        output.stdout
+    };
+
+    for args in [
+        &["oldest", "--timestamp", "--show-source"][..],
+        &["latest", "--timestamp", "--show-source"],
+        &["hist", "--by-source"],
+    ] {
+        let sequential = run(args, "1");
+        assert!(!sequential.is_empty());
+        for jobs in ["2", "16"] {
+            assert_eq!(run(args, jobs), sequential, "{args:?} --jobs {jobs}");
+        }
+    }
+}
+
+#[test]
+fn test_hist_by_source_prints_legend() {
+    let temp = tempdir().unwrap();
+    copy_fixture("scanned_negative.jpg", temp.path());
+    copy_fixture("sample_exif.jpg", temp.path());
+
+    ptime()
+        .arg("hist")
+        .arg("--by-source")
+        .arg("--width")
+        .arg("2")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "2004 \u{2593}\u{2593} 1\n2005  0\n",
+        ))
+        .stdout(predicate::str::ends_with(
+            "2025 \u{2588}\u{2588} 1\n\u{2588} original  \u{2593} digitized\n",
+        ));
+}
+
+#[test]
+fn test_heic_photos_are_scanned() {
+    let temp = tempdir().unwrap();
+    copy_fixture("iphone.heic", temp.path());
+    copy_fixture("sample_exif.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg("--timestamp")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("iphone.heic 2022-08-14T17:45:03+02:00\n");
+}
+
+#[test]
+fn test_hist_counts_raw_files() {
+    let temp = tempdir().unwrap();
+    copy_fixture("nikon.nef", temp.path());
+    copy_fixture("panasonic.rw2", temp.path());
+    copy_fixture("olympus.orf", temp.path());
+
+    ptime()
+        .arg("hist")
+        .arg("--width")
+        .arg("3")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("2018 \u{2588}\u{2588}\u{2588} 3\n");
+}
+
+#[test]
+fn test_png_and_webp_exports_are_dated() {
+    let temp = tempdir().unwrap();
+    copy_fixture("export.png", temp.path());
+    copy_fixture("export.webp", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("export.webp 2016-04-22\n");
+
+    ptime()
+        .arg("latest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("export.png 2017-11-03\n");
+}
+
+#[test]
+fn test_videos_share_the_timeline_with_photos() {
+    let temp = tempdir().unwrap();
+    copy_fixture("iphone_clip.mov", temp.path());
+    copy_fixture("camera_clip.mp4", temp.path());
+    copy_fixture("sample_exif.jpg", temp.path());
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("camera_clip.mp4 2015-08-02 [mvhd]\n");
+
+    ptime()
+        .arg("latest")
+        .arg("--media")
+        .arg("videos")
+        .arg("--timestamp")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("iphone_clip.mov 2019-12-24T18:30:00+01:00\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--media")
+        .arg("photos")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2025-06-07\n");
+}
+
+#[test]
+fn test_xmp_sidecar_corrects_capture_date() {
+    let temp = tempdir().unwrap();
+    copy_fixture("sample_exif.jpg", temp.path());
+    fs::write(
+        temp.path().join("sample_exif.jpg.xmp"),
+        r#"<x:xmpmeta><rdf:RDF><rdf:Description
+            photoshop:DateCreated="2001-09-08T17:00:00"/></rdf:RDF></x:xmpmeta>"#,
+    )
+    .unwrap();
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2001-09-08 [xmp]\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .arg("--date-source")
+        .arg("original,xmp")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2025-06-07 [original]\n");
+}
+
+#[test]
+fn test_takeout_sidecars_date_stripped_files() {
+    let temp = tempdir().unwrap();
+    // Takeout exports often carry no EXIF at all
+    let long_name = "Screenshot_20190401-095236_Maps_and_navigation.jpg";
+    fs::write(temp.path().join(long_name), b"\xFF\xD8\xFF\xD9").unwrap();
+    fs::write(
+        temp.path()
+            .join("Screenshot_20190401-095236_Maps_and_navigation.json"),
+        r#"{"title": "x", "photoTakenTime": {"timestamp": "1554112356"}}"#,
+    )
+    .unwrap();
+    fs::write(temp.path().join("IMG_0007(2).jpg"), b"\xFF\xD8\xFF\xD9").unwrap();
+    fs::write(
+        temp.path().join("IMG_0007.jpg(2).json"),
+        r#"{"photoTakenTime": {"timestamp": "1262304000", "formatted": "1 Jan 2010"}}"#,
+    )
+    .unwrap();
+
+    ptime()
+        .arg("hist")
+        .arg("--width")
+        .arg("1")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("2010 \u{2588} 1\n"))
+        .stdout(predicate::str::ends_with("2019 \u{2588} 1\n"));
+
+    ptime()
+        .arg("latest")
+        .arg("--timestamp")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(format!("{long_name} 2019-04-01T09:52:36+00:00 [takeout]\n"));
+}
+
+#[test]
+fn test_filename_dates_fill_in_for_missing_exif() {
+    let temp = tempdir().unwrap();
+    copy_fixture("sample_exif.jpg", temp.path());
+    for name in [
+        "IMG-20190715-WA0001.jpg",
+        "PXL_20230102_101112345.jpg",
+        "scan 30.06.1987.png",
+    ] {
+        fs::write(temp.path().join(name), b"no metadata").unwrap();
+    }
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("IMG-20190715-WA0001.jpg 2019-07-15 [filename]\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--filename-pattern")
+        .arg("%d.%m.%Y")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("scan 30.06.1987.png 1987-06-30\n");
+}
+
+#[test]
+fn test_date_only_file_names_stay_out_of_hour_buckets() {
+    let temp = tempdir().unwrap();
+    // Saturday 2025-06-07 11:50
+    copy_fixture("sample_exif.jpg", temp.path());
+    fs::write(temp.path().join("IMG-20190715-WA0001.jpg"), b"no metadata").unwrap();
+
+    ptime()
+        .args(["oldest", "--timestamp"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("IMG-20190715-WA0001.jpg 2019-07-15\n");
+
+    let output = ptime()
+        .args(["hist", "--by", "hour", "--width", "1"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "00:00  0");
+    assert_eq!(lines[11], "11:00 \u{2588} 1");
+    assert_eq!(lines[24], "(1 photo without a time of day)");
+
+    let output = ptime()
+        .arg("punchcard")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    // Monday 2019-07-15 gets nothing, not a midnight photo
+    assert!(lines[1].starts_with("Mon ") && lines[1].ends_with(" 0"));
+    assert_eq!(lines.last(), Some(&"(1 photo without a time of day)"));
+}
+
+#[test]
+fn test_fallback_mtime_is_opt_in_and_flagged() {
+    let temp = tempdir().unwrap();
+    let path = temp.path().join("print_042.tif");
+    fs::write(&path, b"scanned without metadata").unwrap();
+    // 2001-09-09T01:46:40Z; dates are rendered in local time
+    let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    ptime()
+        .arg("oldest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("");
+
+    ptime()
+        .arg("oldest")
+        .arg("--fallback")
+        .arg("mtime")
+        .arg("--timestamp")
+        .arg("--show-source")
+        .arg("--tz")
+        .arg("utc")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("print_042.tif 2001-09-09T01:46:40+00:00 [mtime] (low confidence)\n");
+}
+
+#[test]
+fn test_directory_names_date_undated_scans() {
+    let temp = tempdir().unwrap();
+    let vacation = temp.path().join("1987/07 - Vacation");
+    let party = temp.path().join("1987/1987-12-31 Party");
+    let loose = temp.path().join("1985 misc");
+    for dir in [&vacation, &party, &loose] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(vacation.join("scan_01.tif"), b"no metadata").unwrap();
+    fs::write(party.join("scan_02.tif"), b"no metadata").unwrap();
+    fs::write(loose.join("scan_03.tif"), b"no metadata").unwrap();
+
+    // Directory names are only read when asked for
+    ptime()
+        .arg("oldest")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("");
+
+    ptime()
+        .arg("hist")
+        .arg("--width")
+        .arg("2")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("1985 \u{2588} 1\n1986  0\n1987 \u{2588}\u{2588} 2\n");
+
+    ptime()
+        .arg("oldest")
+        .arg("--show-source")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("1985 misc/scan_03.tif 1985 [dir]\n");
+
+    ptime()
+        .arg("latest")
+        .arg("--timestamp")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("1987/1987-12-31 Party/scan_02.tif 1987-12-31\n");
+}
+
+#[test]
+fn test_approximate_years_are_kept_and_marked() {
+    let temp = tempdir().unwrap();
+    let grandma = temp.path().join("ca. 1962 Grandma");
+    let wedding = temp.path().join("1962-06 Wedding");
+    for dir in [&grandma, &wedding] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(grandma.join("scan_01.tif"), b"no metadata").unwrap();
+    fs::write(wedding.join("scan_02.tif"), b"no metadata").unwrap();
+
+    ptime()
+        .arg("oldest")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("ca. 1962 Grandma/scan_01.tif 1962 (approx.)\n");
+
+    ptime()
+        .arg("latest")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("1962-06 Wedding/scan_02.tif 1962-06\n");
+
+    ptime()
+        .arg("hist")
+        .arg("--width")
+        .arg("2")
+        .args(["--date-source", "dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("1962 \u{2588}\u{2588} 2\n");
+}
+
+#[test]
+fn test_cache_skips_unchanged_files() {
+    let temp = tempdir().unwrap();
+    let photos = temp.path().join("photos");
+    fs::create_dir(&photos).unwrap();
+    copy_fixture("sample_exif.jpg", &photos);
+    backdate(&photos.join("sample_exif.jpg"));
+    let cache = temp.path().join("cache.tsv");
+
+    let latest = |extra: &str| {
+        let mut cmd = Command::cargo_bin("ptime").unwrap();
+        cmd.arg("latest").arg("--timestamp").arg(extra).arg(&photos);
+        cmd
+    };
+    let cache_arg = format!("--cache={}", cache.display());
+    latest(&cache_arg)
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2025-06-07T11:50:11\n");
+
+    // A date planted in the cache proves the file is not opened again
+    let contents = fs::read_to_string(&cache).unwrap();
+    assert!(contents.contains("2025-06-07T11:50:11"));
+    fs::write(
+        &cache,
+        contents.replace("2025-06-07T11:50:11", "2001-02-03T04:05:06"),
+    )
+    .unwrap();
+    latest(&cache_arg)
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2001-02-03T04:05:06\n");
+    latest("--no-cache")
+        .assert()
+        .success()
+        .stdout("sample_exif.jpg 2025-06-07T11:50:11\n");
+
+    fs::remove_file(photos.join("sample_exif.jpg")).unwrap();
+    Command::cargo_bin("ptime")
+        .unwrap()
+        .args(["cache", "prune"])
+        .arg(&cache_arg)
+        .assert()
+        .success()
+        .stdout("Removed 1 stale entry, 0 kept\n");
+    Command::cargo_bin("ptime")
+        .unwrap()
+        .args(["cache", "clear"])
+        .arg(&cache_arg)
+        .assert()
+        .success();
+    assert!(!cache.exists());
+}
+
+#[test]
+fn test_cache_keeps_directory_dates_per_scan_root() {
+    let temp = tempdir().unwrap();
+    let july = temp.path().join("photos/2019/07");
+    fs::create_dir_all(&july).unwrap();
+    fs::write(july.join("scan_01.tif"), b"no metadata").unwrap();
+    backdate(&july.join("scan_01.tif"));
+    let cache_arg = format!("--cache={}", temp.path().join("cache.tsv").display());
+
+    let oldest = |root: &Path| {
+        let mut cmd = Command::cargo_bin("ptime").unwrap();
+        cmd.args(["oldest", "--date-source", "dir"])
+            .arg(&cache_arg)
+            .arg(root);
+        cmd
+    };
+    // Below its dated directories, the scan has nothing to date it by
+    oldest(&july).assert().success().stdout("");
+    oldest(&temp.path().join("photos"))
+        .assert()
+        .success()
+        .stdout("2019/07/scan_01.tif 2019-07\n");
+    oldest(&july).assert().success().stdout("");
+}
+
+#[test]
+fn test_hist_by_month() {
+    let temp = tempdir().unwrap();
+    copy_fixture("sample_exif.jpg", temp.path());
+    let trip = temp.path().join("2025-03 Trip");
+    let scans = temp.path().join("1987");
+    for dir in [&trip, &scans] {
+        fs::create_dir(dir).unwrap();
+    }
+    fs::write(trip.join("scan_01.tif"), b"no metadata").unwrap();
+    fs::write(scans.join("scan_02.tif"), b"no metadata").unwrap();
+
+    ptime()
+        .args(["hist", "--by", "month", "--width", "1"])
+        .args(["--date-source", "original,dir"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(
+            "2025-03 \u{2588} 1\n\
+             2025-04  0\n\
+             2025-05  0\n\
+             2025-06 \u{2588} 1\n\
+             (1 photo not dated to the month)\n",
+        );
+}
+
+#[test]
+fn test_since_and_until_limit_every_command() {
+    let temp = tempdir().unwrap();
+    // Taken 2019-07-15, 2021-01-01 and 2025-06-07
+    copy_fixture("slash_date.jpg", temp.path());
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+    copy_fixture("sample_exif.jpg", temp.path());
+
+    ptime()
+        .args(["latest", "--until", "2024"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2021-01-01\n");
+
+    ptime()
+        .args(["oldest", "--since", "2019-08"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("tokyo_new_year.jpg 2021-01-01\n");
+
+    ptime()
+        .args(["hist", "--width", "1", "--since", "2020", "--until", "2022"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("2021 \u{2588} 1\n");
+
+    ptime()
+        .args(["hist", "--since", "2019", "--until", "2018"])
+        .arg(temp.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_calendar_shows_latest_year_by_default() {
+    let temp = tempdir().unwrap();
+    // Taken 2021-01-01 (a Friday) and 2025-06-07 (a Saturday)
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+    copy_fixture("sample_exif.jpg", temp.path());
+
+    let output = ptime()
+        .arg("calendar")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "2025: 1 photo on 1 day");
+    assert_eq!(lines.len(), 10);
+    // 2025 starts on a Wednesday; June 7 is in the 23rd week
+    assert_eq!(lines[7].chars().nth(4 + 22), Some('\u{2588}'));
+
+    ptime()
+        .args(["calendar", "--year", "2021"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("2021: 1 photo on 1 day\n"))
+        .stdout(predicate::str::contains("\nFri \u{2588}\u{00B7}"));
+
+    ptime()
+        .args(["calendar", "--year", "2022"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_punchcard_places_photos_by_weekday_and_hour() {
+    let temp = tempdir().unwrap();
+    // Saturday 2025-06-07 11:50 and Friday 2021-01-01 01:00 (+09:00)
+    copy_fixture("sample_exif.jpg", temp.path());
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+    let cell = |hour: usize| 4 + hour * 3;
+
+    let output = ptime()
+        .arg("punchcard")
+        .arg(temp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert_eq!(lines[5].chars().nth(cell(1)), Some('\u{2588}'));
+    assert!(lines[5].starts_with("Fri ") && lines[5].ends_with(" 1"));
+    assert_eq!(lines[6].chars().nth(cell(11)), Some('\u{2588}'));
+
+    // In UTC the Tokyo photo moves to Thursday 16:00
+    let output = ptime()
+        .args(["punchcard", "--tz", "utc"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[4].chars().nth(cell(16)), Some('\u{2588}'));
+    assert!(lines[5].ends_with(" 0"));
+}
+
+#[test]
+fn test_stats_reports_counts_dates_and_skip_reasons() {
+    let temp = tempdir().unwrap();
+    copy_fixture("sample_exif.jpg", temp.path());
+    copy_fixture("tokyo_new_year.jpg", temp.path());
+    copy_fixture("slash_date.jpg", temp.path());
+    // No EXIF at all, then an EXIF segment that cannot be parsed
+    fs::write(temp.path().join("blank.jpg"), b"\xff\xd8\xff\xd9").unwrap();
+    fs::write(
+        temp.path().join("broken.jpg"),
+        b"\xff\xd8\xff\xe1\x00\x10Exif\x00\x00garbage!!",
+    )
+    .unwrap();
+
+    ptime()
+        .args(["stats", "--since", "2020"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "Supported files scanned:  5\n\
+             Files dated:              2\n\
+             Files skipped:            3\n\
+             \x20 no date:                1\n\
+             \x20 unreadable metadata:    1\n\
+             \x20 outside date range:     1\n\
+             Earliest:                 tokyo_new_year.jpg 2021-01-01\n\
+             Latest:                   sample_exif.jpg 2025-06-07\n\
+             Span:                     1618 days (4.4 years)\n\
+             Median:                   2021-01-01\n\
+             Percentiles:              p10 2021-01-01  p25 2021-01-01  p75 2025-06-07  p90 2025-06-07\n\
+             Busiest year:             2021 (1 photo)\n\
+             Busiest month:            2021-01 (1 photo)\n\
+             Busiest day:              2021-01-01 (1 photo)\n\
+             Active days:              2\n\
+             Photos per active day:    1.0\n",
+        ));
+}
+
+#[test]
+fn test_stats_empty_directory_prints_nothing() {
+    let temp = tempdir().unwrap();
+
+    ptime()
+        .args(["stats"])
+        .arg(temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}